@@ -0,0 +1,133 @@
+//! Synchronous versions of the query methods, for code that doesn't run inside an async runtime.
+//!
+//! They share the builders, models and errors of the async API and simply wait for it on a
+//! small runtime owned by this module, so everything configured on a
+//! [`ClientBuilder`](crate::shared::client::ClientBuilder) applies here too. They must not be
+//! called from inside an async runtime.
+//!
+//! ```no_run
+//! use rusty_booru::{
+//!     blocking::BlockingQueryDispatcher,
+//!     danbooru::client::DanbooruClient,
+//!     shared::client::WithClientBuilder,
+//! };
+//!
+//! let posts = DanbooruClient::builder()
+//!     .query(|q| q.tag("kafuu_chino").limit(5))
+//!     .get_blocking()
+//!     .expect("There was an error retrieving posts from the API");
+//! ```
+
+use std::{future::Future, sync::OnceLock};
+
+use crate::{
+    generic::{
+        client::{BooruOption, GenericClient},
+        AutoCompleteItem, BooruPost,
+    },
+    shared::{
+        self,
+        client::{self, ClientQueryBuilder, ClientTypes},
+    },
+};
+
+/// Waits for `future` on the runtime shared by the blocking API.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("rusty-booru-blocking")
+                .enable_all()
+                .build()
+                .expect("the blocking runtime to be built")
+        })
+        .block_on(future)
+}
+
+/// Blocking counterpart of [`client::QueryDispatcher`], implemented for every dispatcher. The
+/// methods carry a `_blocking` suffix so both traits can be in scope at once.
+pub trait BlockingQueryDispatcher<T: ClientTypes> {
+    fn get_autocomplete_blocking<In: Into<String> + Send>(
+        &self,
+        input: In,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error>;
+
+    fn get_by_id_blocking(&self, id: u32) -> Result<Option<T::Post>, shared::Error>;
+
+    fn get_blocking(&self) -> Result<Vec<T::Post>, shared::Error>;
+}
+
+impl<T: ClientTypes, D: client::QueryDispatcher<T>> BlockingQueryDispatcher<T> for D {
+    fn get_autocomplete_blocking<In: Into<String> + Send>(
+        &self,
+        input: In,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        block_on(self.get_autocomplete(input))
+    }
+
+    fn get_by_id_blocking(&self, id: u32) -> Result<Option<T::Post>, shared::Error> {
+        block_on(self.get_by_id(id))
+    }
+
+    fn get_blocking(&self) -> Result<Vec<T::Post>, shared::Error> {
+        block_on(self.get())
+    }
+}
+
+impl ClientQueryBuilder<GenericClient> {
+    /// Blocking version of [`ClientQueryBuilder::get_autocomplete`].
+    pub fn get_autocomplete_blocking<In: Into<String> + Send>(
+        &self,
+        booru: BooruOption,
+        input: In,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        block_on(self.get_autocomplete(booru, input))
+    }
+
+    /// Blocking version of [`ClientQueryBuilder::get_by_id`].
+    pub fn get_by_id_blocking(
+        &self,
+        id: u32,
+        booru: BooruOption,
+    ) -> Result<Option<BooruPost>, shared::Error> {
+        block_on(self.get_by_id(id, booru))
+    }
+
+    /// Blocking version of [`ClientQueryBuilder::get`].
+    pub fn get_blocking(&self, booru: BooruOption) -> Result<Vec<BooruPost>, shared::Error> {
+        block_on(self.get(booru))
+    }
+}
+
+impl GenericClient {
+    /// Blocking version of [`GenericClient::get_autocomplete`].
+    pub fn get_autocomplete_blocking<In: Into<String> + Send>(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+        booru: BooruOption,
+        input: In,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        block_on(self.get_autocomplete(query, booru, input))
+    }
+
+    /// Blocking version of [`GenericClient::get_by_id`].
+    pub fn get_by_id_blocking(
+        &self,
+        id: u32,
+        booru: BooruOption,
+    ) -> Result<Option<BooruPost>, shared::Error> {
+        block_on(self.get_by_id(id, booru))
+    }
+
+    /// Blocking version of [`GenericClient::get`].
+    pub fn get_blocking(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+        booru: BooruOption,
+    ) -> Result<Vec<BooruPost>, shared::Error> {
+        block_on(self.get(query, booru))
+    }
+}
@@ -1,29 +1,33 @@
+use std::num::NonZeroU32;
+
 use derive_more::From;
-use reqwest::{header, header::HeaderMap, Response};
+use itertools::Itertools;
+use reqwest::StatusCode;
 
-use super::*;
+use super::{instance::Quirks, *};
 use crate::{
     generic::AutoCompleteItem,
-    shared::{self, client::*},
+    shared::{
+        self,
+        client::*,
+        lenient,
+        rate_limit::RateLimit,
+        response::RawResponse,
+        transport::{Endpoint, HttpRequest},
+        Tag,
+    },
 };
 
-// This is only here because of Danbooru, thanks Danbooru, really cool :)
-pub fn get_headers() -> HeaderMap {
-    let mut headers = header::HeaderMap::new();
-    headers.insert(
-        header::USER_AGENT,
-        header::HeaderValue::from_static("PostmanRuntime/7.30.0"),
-    );
-    headers
-}
-
 /// Client that sends requests to the Danbooru API to retrieve the data.
 #[derive(From, Debug, Clone)]
 pub struct DanbooruClient(pub ClientBuilder<Self>);
 
 impl ClientInformation for DanbooruClient {
+    const NAME: &'static str = "danbooru";
     const URL: &'static str = "https://danbooru.donmai.us";
     const SORT: &'static str = "order:";
+    // Danbooru allows around 10 reads per second, stay well under it.
+    const RATE_LIMIT: RateLimit = RateLimit::per_second(NonZeroU32::new(5).unwrap());
 }
 
 impl ClientTypes for DanbooruClient {
@@ -31,88 +35,233 @@ impl ClientTypes for DanbooruClient {
     type Post = DanbooruPost;
 }
 
-#[derive(Deserialize, Debug, thiserror::Error, Display)]
-pub enum DanbooruError {
-    #[serde(rename = "PostQuery::TagLimitError")]
-    TagLimitError,
+macro_rules! danbooru_errors {
+    ($($(#[$meta:meta])* $variant:ident => $name:literal),* $(,)?) => {
+        /// Error Danbooru reported in the body of a failed response. Every variant holds the
+        /// message Danbooru sent along with the HTTP status it came with.
+        #[derive(Debug, Clone, thiserror::Error)]
+        pub enum DanbooruError {
+            $(
+                $(#[$meta])*
+                #[doc = concat!("`", $name, "`")]
+                #[error("{message}")]
+                $variant { message: String, status: StatusCode },
+            )*
+
+            /// Any error this crate doesn't know about yet.
+            #[error("{name}: {message}")]
+            Other {
+                name: String,
+                message: String,
+                status: StatusCode,
+            },
+        }
+
+        impl DanbooruError {
+            pub fn new(name: String, message: String, status: StatusCode) -> Self {
+                match name.as_str() {
+                    $($name => Self::$variant { message, status },)*
+                    _ => Self::Other { name, message, status },
+                }
+            }
+
+            /// Name of the exception on Danbooru's side, e.g. `PostQuery::TagLimitError`.
+            pub fn name(&self) -> &str {
+                match self {
+                    $(Self::$variant { .. } => $name,)*
+                    Self::Other { name, .. } => name,
+                }
+            }
+
+            pub fn message(&self) -> &str {
+                match self {
+                    $(Self::$variant { message, .. })|* | Self::Other { message, .. } => message,
+                }
+            }
+
+            pub fn status(&self) -> StatusCode {
+                match self {
+                    $(Self::$variant { status, .. })|* | Self::Other { status, .. } => *status,
+                }
+            }
+        }
+    };
+}
+
+danbooru_errors! {
+    /// The query has more tags than the account is allowed to search for.
+    TagLimitError => "PostQuery::TagLimitError",
+    /// The search took too long and was cancelled.
+    TimeoutError => "PostQuery::TimeoutError",
+    RecordNotFound => "ActiveRecord::RecordNotFound",
+    /// Danbooru's database gave up on the query, usually because of its statement timeout.
+    StatementTimeout => "ActiveRecord::QueryCanceled",
+    /// The account isn't allowed to do this, like searching past the page limit.
+    PrivilegeError => "User::PrivilegeError",
+    PaginationError => "PaginationExtension::PaginationError",
+    AuthenticationFailure => "SessionLoader::AuthenticationFailure",
+    BadRequest => "ActionController::BadRequest",
+    ParameterMissing => "ActionController::ParameterMissing",
 }
 
+/// Body of a failed Danbooru response.
 #[derive(Deserialize)]
 struct DanbooruErrorStruct {
-    pub error: DanbooruError,
+    pub error: String,
+    #[serde(default, deserialize_with = "lenient::string")]
+    pub message: String,
 }
 
-impl From<DanbooruErrorStruct> for shared::Error {
-    fn from(value: DanbooruErrorStruct) -> Self {
-        value.error.into()
+/// Danbooru explains most of its failures in the body, which is preferred over the bare status
+/// for client errors. Rate limits and server errors are mapped by status first, so they stay
+/// [`shared::Error::RateLimited`] and [`shared::Error::Server`] whatever the body says.
+fn send_error(response: RawResponse) -> shared::Error {
+    if response.status == StatusCode::TOO_MANY_REQUESTS || response.status.is_server_error() {
+        return response.error();
     }
-}
 
-async fn send_error<T>(response: Response) -> Result<T, shared::Error> {
-    Err(response
+    response
         .json::<DanbooruErrorStruct>()
+        .map(|e| DanbooruError::new(e.error, e.message, response.status).into())
+        .unwrap_or_else(|_| response.error())
+}
+
+impl ClientQueryDispatcher<DanbooruClient> {
+    async fn send(&self, request: HttpRequest) -> Result<RawResponse, shared::Error> {
+        let response = self.builder.send(request).await?;
+
+        if response.status.is_success() {
+            Ok(response)
+        } else {
+            Err(send_error(response))
+        }
+    }
+}
+
+impl ClientQueryDispatcher<DanbooruClient> {
+    /// The query's tags, written the way the instance expects them.
+    fn tags_with(&self, quirks: &Quirks) -> Result<String, shared::Error> {
+        let tags = &self.query.tags.0;
+        let counted = tags
+            .iter()
+            .filter(|tag| matches!(tag, Tag::Plain(_) | Tag::Blacklist(_)))
+            .count();
+
+        if let Some(limit) = quirks.tag_limit.filter(|limit| counted > *limit as usize) {
+            return Err(shared::Error::InvalidQuery(format!(
+                "the search has {counted} tags, the instance allows {limit}"
+            )));
+        }
+
+        Ok(tags
+            .iter()
+            .map(|tag| match tag {
+                Tag::Rating(rating) => format!("rating:{}", quirks.ratings.name(rating)),
+                tag => tag.to_string(),
+            })
+            .join(" "))
+    }
+
+    fn read(&self, quirks: &Quirks, mut post: DanbooruPost) -> DanbooruPost {
+        post.rating = post.rating.map(|rating| quirks.ratings.read(rating));
+        post
+    }
+
+    pub(crate) async fn get_autocomplete_with(
+        &self,
+        quirks: &Quirks,
+        input: String,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        self.traced("get_autocomplete", async move {
+            if !quirks.autocomplete {
+                return Err(shared::Error::Unsupported("autocomplete"));
+            }
+
+            self.send(
+                HttpRequest::get(format!("{}/autocomplete.json", self.builder.url))
+                    .query([
+                        ("limit", self.query.limit.to_string()),
+                        ("search[type]", "tag_query".to_string()),
+                        ("search[query]", input),
+                        ("version", "1".to_string()),
+                    ])
+                    .endpoint(Endpoint::Autocomplete),
+            )
+            .await?
+            .json_or_default()
+        })
+        .await
+    }
+
+    pub(crate) async fn get_by_id_with(
+        &self,
+        quirks: &Quirks,
+        id: u32,
+    ) -> Result<Option<DanbooruPost>, shared::Error> {
+        self.traced("get_by_id", async move {
+            let response = self
+                .send(
+                    HttpRequest::get(format!("{}/posts/{id}.json", self.builder.url))
+                        .endpoint(Endpoint::Post),
+                )
+                .await;
+
+            match response {
+                Ok(response) => response.json().map(|post| Some(self.read(quirks, post))),
+                Err(shared::Error::NotFound)
+                | Err(shared::Error::Danbooru(DanbooruError::RecordNotFound { .. })) => Ok(None),
+                Err(e) => Err(e),
+            }
+        })
         .await
-        .map(Into::into)?)
+    }
+
+    pub(crate) async fn get_with(
+        &self,
+        quirks: &Quirks,
+    ) -> Result<Vec<DanbooruPost>, shared::Error> {
+        self.traced("get", async move {
+            let limit = quirks
+                .max_limit
+                .map_or(self.query.limit, |max| self.query.limit.min(max));
+
+            self.send(
+                HttpRequest::get(format!("{}/posts.json", self.builder.url))
+                    .query([
+                        ("limit", limit.to_string()),
+                        ("tags", self.tags_with(quirks)?),
+                    ])
+                    .query(self.query.page.map(|page| ("page", page)))
+                    .endpoint(Endpoint::Posts),
+            )
+            .await?
+            .json_or_default::<Vec<DanbooruPost>>()
+            .map(|posts| {
+                posts
+                    .into_iter()
+                    .map(|post| self.read(quirks, post))
+                    .collect()
+            })
+        })
+        .await
+    }
 }
 
+/// Upstream Danbooru, with the default [`Quirks`].
 impl QueryDispatcher<DanbooruClient> for ClientQueryDispatcher<DanbooruClient> {
     async fn get_autocomplete<In: Into<String> + Send>(
         &self,
         input: In,
-    ) -> Result<Vec<AutoCompleteItem>, reqwest::Error> {
-        self.builder
-            .client
-            .get(format!("{}/autocomplete.json", self.builder.url))
-            .headers(get_headers())
-            .query(&[
-                ("limit", self.query.limit.to_string().as_str()),
-                ("search[type]", "tag_query"),
-                ("search[query]", &input.into()),
-                ("version", "1"),
-            ])
-            .send()
-            .await?
-            .json::<Vec<AutoCompleteItem>>()
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        self.get_autocomplete_with(&Quirks::default(), input.into())
             .await
     }
 
     async fn get_by_id(&self, id: u32) -> Result<Option<DanbooruPost>, shared::Error> {
-        let response = self
-            .builder
-            .client
-            .get(format!("{}/posts/{id}.json", self.builder.url))
-            .headers(get_headers())
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            response
-                .json::<DanbooruPost>()
-                .await
-                .map(Into::into)
-                .map_err(Into::into)
-        } else {
-            send_error(response).await?
-        }
+        self.get_by_id_with(&Quirks::default(), id).await
     }
 
     async fn get(&self) -> Result<Vec<DanbooruPost>, shared::Error> {
-        let response = self
-            .builder
-            .client
-            .get(format!("{}/posts.json", self.builder.url))
-            .headers(get_headers())
-            .query(&[
-                ("limit", &self.query.limit.to_string()),
-                ("tags", &self.query.tags.unpack()),
-            ])
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            Ok(response.json::<Vec<DanbooruPost>>().await?)
-        } else {
-            send_error(response).await?
-        }
+        self.get_with(&Quirks::default()).await
     }
 }
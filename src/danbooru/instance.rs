@@ -0,0 +1,384 @@
+//! Danbooru forks and other instances of it.
+//!
+//! Instances share Danbooru's API but not always its settings: older forks still use the ratings
+//! Danbooru had before 2022, and how many tags a search can hold depends on the instance as much
+//! as on the account. An [`InstanceConfig`] declares what is known about one, and an [`Instance`]
+//! completes it on first use by asking the instance itself:
+//!
+//! - `/profile.json` for the tag limit of the account the requests are sent with,
+//! - `/autocomplete.json` for whether tags can be autocompleted.
+//!
+//! Requests then go through the same code as [`DanbooruClient`]'s, which is nothing but an
+//! instance with [`Quirks::default`].
+
+use std::{
+    future::poll_fn,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{client::DanbooruClient, DanbooruPost, DanbooruRating};
+use crate::{
+    generic::{booru::Booru, client::GenericClient, AutoCompleteItem, BooruPost},
+    shared::{
+        self,
+        client::{ClientBuilder, ClientQueryBuilder},
+        lenient,
+        response::RawResponse,
+        transport::{BoxFuture, Endpoint, HttpRequest},
+    },
+};
+
+/// How long an instance that couldn't be probed is used with its declared quirks before being
+/// probed again, doubled after every failure up to the second value.
+const DETECT_BACKOFF: (Duration, Duration) = (Duration::from_secs(30), Duration::from_secs(1800));
+
+/// How an instance names its ratings.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RatingScheme {
+    /// General, sensitive, questionable and explicit, what Danbooru uses since 2022.
+    #[default]
+    Current,
+    /// Safe, questionable and explicit. Safe covers both general and sensitive posts, which are
+    /// read back as general.
+    Legacy,
+}
+
+impl RatingScheme {
+    /// Name of `rating` in search queries.
+    pub fn name(&self, rating: &DanbooruRating) -> String {
+        match (self, rating) {
+            (Self::Current, rating) => rating.to_string(),
+            (Self::Legacy, DanbooruRating::General | DanbooruRating::Sensitive) => "s".into(),
+            (Self::Legacy, DanbooruRating::Questionable) => "q".into(),
+            (Self::Legacy, DanbooruRating::Explicit) => "e".into(),
+        }
+    }
+
+    /// `rating` as it was meant by the instance, `s` being read as sensitive otherwise.
+    pub fn read(&self, rating: DanbooruRating) -> DanbooruRating {
+        match (self, rating) {
+            (Self::Legacy, DanbooruRating::Sensitive) => DanbooruRating::General,
+            (_, rating) => rating,
+        }
+    }
+}
+
+fn yes() -> bool {
+    true
+}
+
+/// What the requests to an instance have to account for.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Quirks {
+    #[serde(default)]
+    pub ratings: RatingScheme,
+    /// Most tags a search can hold, rating and order metatags aside. Searches with more fail
+    /// before being sent. `None` leaves it to the instance.
+    #[serde(default)]
+    pub tag_limit: Option<u32>,
+    /// Most posts a page can hold, larger limits are lowered to it.
+    #[serde(default)]
+    pub max_limit: Option<u32>,
+    #[serde(default = "yes")]
+    pub autocomplete: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            ratings: RatingScheme::default(),
+            tag_limit: None,
+            max_limit: None,
+            autocomplete: true,
+        }
+    }
+}
+
+/// What probing an instance found out, see [`Instance::detect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Tag limit of the account, `None` when the instance didn't tell.
+    pub tag_limit: Option<u32>,
+    pub autocomplete: bool,
+}
+
+impl Quirks {
+    /// These quirks, corrected with what was detected. Autocompletion declared as unsupported
+    /// stays so whatever the probe said.
+    pub fn with(&self, capabilities: &Capabilities) -> Self {
+        Self {
+            tag_limit: capabilities.tag_limit.or(self.tag_limit),
+            autocomplete: self.autocomplete && capabilities.autocomplete,
+            ..self.clone()
+        }
+    }
+}
+
+/// Everything needed to talk to a Danbooru instance.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InstanceConfig {
+    /// Name the instance is registered under.
+    pub name: String,
+    /// Base url, without the trailing slash.
+    pub url: String,
+    #[serde(flatten)]
+    pub quirks: Quirks,
+    /// Probe the instance before its first request, see [`Instance::detect`].
+    #[serde(default = "yes")]
+    pub detect: bool,
+}
+
+impl InstanceConfig {
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            quirks: Quirks::default(),
+            detect: true,
+        }
+    }
+
+    pub fn danbooru() -> Self {
+        Self::new("danbooru", "https://danbooru.donmai.us")
+    }
+
+    /// Danbooru's test instance, which anyone can write to.
+    pub fn testbooru() -> Self {
+        Self::new("testbooru", "https://testbooru.donmai.us")
+    }
+
+    /// Danbooru limited to general posts.
+    pub fn safebooru() -> Self {
+        Self::new("safebooru.donmai.us", "https://safebooru.donmai.us")
+    }
+
+    pub fn aibooru() -> Self {
+        Self::new("aibooru", "https://aibooru.online")
+    }
+}
+
+/// The part of `/profile.json` that matters here.
+#[derive(Deserialize, Default)]
+struct Profile {
+    #[serde(default, deserialize_with = "lenient::option_number")]
+    tag_query_limit: Option<u32>,
+}
+
+/// Where detecting the quirks of an [`Instance`] is at.
+#[derive(Debug, Default)]
+struct Detection {
+    /// Quirks corrected by a successful probe.
+    quirks: Option<Quirks>,
+    /// Whether a call is probing the instance, the others wait for it to be done.
+    probing: bool,
+    waiting: Vec<Waker>,
+    /// When the instance may be probed again after a failure.
+    retry_at: Option<Instant>,
+    backoff: Option<Duration>,
+}
+
+/// Lets the calls waiting for a probe go once it is done or dropped.
+struct Probing<'a>(&'a Mutex<Detection>);
+
+impl Drop for Probing<'_> {
+    fn drop(&mut self) {
+        let mut detection = self.0.lock().unwrap();
+        detection.probing = false;
+        detection.waiting.drain(..).for_each(Waker::wake);
+    }
+}
+
+/// Client of a Danbooru instance described by an [`InstanceConfig`].
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub config: Arc<InstanceConfig>,
+    /// Sends the requests, change it to set up authentication, rate limits and so on.
+    pub builder: ClientBuilder<DanbooruClient>,
+    /// Shared between clones so the instance is probed once.
+    detection: Arc<Mutex<Detection>>,
+}
+
+impl Instance {
+    pub fn new(config: InstanceConfig) -> Self {
+        let mut builder = ClientBuilder::new();
+        builder.default_url(&config.url);
+
+        Self {
+            config: Arc::new(config),
+            builder,
+            detection: Arc::default(),
+        }
+    }
+
+    /// Asks the instance about its limits and capabilities. The tag limit comes from the profile
+    /// of the account the builder is set up with, which is the anonymous one by default.
+    /// Autocompletion is only found unsupported when its endpoint doesn't exist, rate limits and
+    /// server errors fail the detection.
+    pub async fn detect(&self) -> Result<Capabilities, shared::Error> {
+        let url = &self.builder.url;
+
+        let profile = probe(
+            self.builder
+                .send(HttpRequest::get(format!("{url}/profile.json")).endpoint(Endpoint::Other))
+                .await?,
+        )?;
+        let tag_limit = if profile.status.is_success() {
+            profile
+                .json::<Profile>()
+                .unwrap_or_default()
+                .tag_query_limit
+        } else {
+            None
+        };
+
+        let autocomplete = probe(
+            self.builder
+                .send(
+                    HttpRequest::get(format!("{url}/autocomplete.json"))
+                        .query([
+                            ("search[query]", "a"),
+                            ("search[type]", "tag_query"),
+                            ("limit", "1"),
+                        ])
+                        .endpoint(Endpoint::Autocomplete),
+                )
+                .await?,
+        )?;
+
+        Ok(Capabilities {
+            tag_limit,
+            autocomplete: !matches!(
+                autocomplete.status,
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+            ),
+        })
+    }
+
+    /// The quirks requests are sent with. Detection runs the first time if the config asks for
+    /// it, with concurrent calls waiting for the same probe. An instance that can't be probed is
+    /// used with its declared quirks, and probed again after a backoff.
+    pub async fn quirks(&self) -> Quirks {
+        if !self.config.detect {
+            return self.config.quirks.clone();
+        }
+
+        loop {
+            let known = poll_fn(|cx| {
+                let mut detection = self.detection.lock().unwrap();
+
+                if let Some(quirks) = &detection.quirks {
+                    return Poll::Ready(Some(quirks.clone()));
+                }
+                if detection.retry_at.is_some_and(|at| Instant::now() < at) {
+                    return Poll::Ready(Some(self.config.quirks.clone()));
+                }
+                if detection.probing {
+                    detection.waiting.push(cx.waker().clone());
+                    return Poll::Pending;
+                }
+
+                detection.probing = true;
+                Poll::Ready(None)
+            })
+            .await;
+
+            if let Some(quirks) = known {
+                return quirks;
+            }
+
+            let probing = Probing(&self.detection);
+            let result = self.detect().await;
+
+            let mut detection = self.detection.lock().unwrap();
+            match result {
+                Ok(capabilities) => {
+                    detection.quirks = Some(self.config.quirks.with(&capabilities));
+                }
+                Err(_) => {
+                    let (initial, max) = DETECT_BACKOFF;
+                    let backoff = detection.backoff.map_or(initial, |b| (b * 2).min(max));
+                    detection.backoff = Some(backoff);
+                    detection.retry_at = Some(Instant::now() + backoff);
+                }
+            }
+            drop(detection);
+            drop(probing);
+        }
+    }
+
+    pub async fn get(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+    ) -> Result<Vec<DanbooruPost>, shared::Error> {
+        let quirks = self.quirks().await;
+        self.builder
+            .query_raw(&mut query.convert())
+            .get_with(&quirks)
+            .await
+    }
+
+    pub async fn get_by_id(&self, id: u32) -> Result<Option<DanbooruPost>, shared::Error> {
+        let quirks = self.quirks().await;
+        self.builder.dispatch().get_by_id_with(&quirks, id).await
+    }
+
+    pub async fn get_autocomplete(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+        input: impl Into<String>,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        let quirks = self.quirks().await;
+        self.builder
+            .query_raw(&mut query.convert())
+            .get_autocomplete_with(&quirks, input.into())
+            .await
+    }
+}
+
+impl Booru for Instance {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn get<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+    ) -> BoxFuture<'a, Result<Vec<BooruPost>, shared::Error>> {
+        Box::pin(async move {
+            Instance::get(self, query)
+                .await
+                .map(|v| v.into_iter().map(Into::into).collect())
+        })
+    }
+
+    fn get_by_id(&self, id: u32) -> BoxFuture<'_, Result<Option<BooruPost>, shared::Error>> {
+        Box::pin(async move {
+            Instance::get_by_id(self, id)
+                .await
+                .map(|v| v.map(Into::into))
+        })
+    }
+
+    fn get_autocomplete<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+        input: String,
+    ) -> BoxFuture<'a, Result<Vec<AutoCompleteItem>, shared::Error>> {
+        Box::pin(Instance::get_autocomplete(self, query, input))
+    }
+}
+
+/// `response` of a probe, unless it says to come back later.
+fn probe(response: RawResponse) -> Result<RawResponse, shared::Error> {
+    match response.status {
+        StatusCode::TOO_MANY_REQUESTS => Err(response.error()),
+        status if status.is_server_error() => Err(response.error()),
+        _ => Ok(response),
+    }
+}
@@ -1,56 +1,112 @@
 pub mod client;
+pub mod instance;
 
 use derive_more::From;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use strum::Display;
 
-use crate::generic::{BooruPost, Rating};
+use crate::{
+    generic::{BooruPost, Rating},
+    shared::{
+        interner::{InternTags, Interned, InternedTags, TagId, TagInterner},
+        lenient,
+        pages::WithId,
+    },
+};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct DanbooruPost {
+    #[serde(deserialize_with = "lenient::required_number")]
     pub id: u32,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub created_at: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub updated_at: String,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub uploader_id: u32,
+    #[serde(default, deserialize_with = "lenient::option_number")]
     pub approver_id: Option<u32>,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tag_string: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tag_string_general: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tag_string_artist: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tag_string_copyright: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tag_string_character: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tag_string_meta: String,
+    #[serde(default, deserialize_with = "lenient::known")]
     pub rating: Option<DanbooruRating>,
+    #[serde(default, deserialize_with = "lenient::option_number")]
     pub parent_id: Option<u32>,
+    #[serde(default, deserialize_with = "lenient::option_number")]
     pub pixiv_id: Option<u32>,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub source: String,
+    #[serde(default, deserialize_with = "lenient::option_string")]
     pub md5: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string")]
     pub file_url: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string")]
     pub large_file_url: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string")]
     pub preview_file_url: Option<String>,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub file_ext: String,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub file_size: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub image_width: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub image_height: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub score: i32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub up_score: i32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub down_score: i32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub fav_count: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub tag_count_general: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub tag_count_artist: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub tag_count_copyright: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub tag_count_character: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub tag_count_meta: u32,
+    #[serde(default, deserialize_with = "lenient::option_string")]
     pub last_comment_bumped_at: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string")]
     pub last_noted_at: Option<String>,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub has_large: bool,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub has_children: bool,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub has_visible_children: bool,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub has_active_children: bool,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub is_banned: bool,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub is_deleted: bool,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub is_flagged: bool,
+    #[serde(default, deserialize_with = "lenient::boolean")]
     pub is_pending: bool,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub bit_flags: u32,
+
+    /// Any field that isn't modeled above, kept as sent by the API.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Post's rating. Check the [Danbooru's ratings wiki](https://danbooru.donmai.us/wiki_pages/howto:rate)
@@ -104,7 +160,66 @@ impl From<DanbooruPost> for BooruPost {
             tags: value.tag_string,
             image: None,
             source: value.source.into(),
-            rating: value.rating.unwrap_or(DanbooruRating::Sensitive).into(),
+            rating: value.rating.map(Into::into),
         }
     }
 }
+
+/// Interned tags of a [`DanbooruPost`], split by category like the `tag_string_*` fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DanbooruTags {
+    pub general: InternedTags,
+    pub artist: InternedTags,
+    pub copyright: InternedTags,
+    pub character: InternedTags,
+    pub meta: InternedTags,
+}
+
+impl DanbooruTags {
+    /// Every tag of the post, in the same order as categories are listed above.
+    pub fn all(&self) -> impl Iterator<Item = TagId> + '_ {
+        [
+            &self.general,
+            &self.artist,
+            &self.copyright,
+            &self.character,
+            &self.meta,
+        ]
+        .into_iter()
+        .flat_map(|tags| tags.ids().iter().copied())
+    }
+}
+
+impl From<DanbooruTags> for InternedTags {
+    fn from(value: DanbooruTags) -> Self {
+        value.all().collect()
+    }
+}
+
+impl WithId for DanbooruPost {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl InternTags for DanbooruPost {
+    type Tags = DanbooruTags;
+
+    fn intern_tags(mut self, interner: &TagInterner) -> Interned<Self> {
+        let take = |tags: &mut String| interner.intern_all(&std::mem::take(tags));
+
+        // `tag_string` is just every category joined together, so it's dropped rather than
+        // interned a second time.
+        self.tag_string = String::new();
+
+        let tags = DanbooruTags {
+            general: take(&mut self.tag_string_general),
+            artist: take(&mut self.tag_string_artist),
+            copyright: take(&mut self.tag_string_copyright),
+            character: take(&mut self.tag_string_character),
+            meta: take(&mut self.tag_string_meta),
+        };
+
+        Interned { post: self, tags }
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
             ClientBuilder, ClientInformation, ClientQueryDispatcher, ClientTypes,
             ImplementedWithCommonQuery, QueryDispatcher, QueryLike, QueryMode, WithCommonQuery,
         },
+        transport::{Endpoint, HttpRequest},
     },
 };
 
@@ -18,6 +19,7 @@ use super::*;
 pub struct GelbooruClient(pub ClientBuilder<Self>);
 
 impl ClientInformation for GelbooruClient {
+    const NAME: &'static str = "gelbooru";
     const URL: &'static str = "https://gelbooru.com";
     const SORT: &'static str = "sort:";
 }
@@ -37,45 +39,55 @@ impl QueryDispatcher<GelbooruClient> for ClientQueryDispatcher<GelbooruClient> {
     async fn get_autocomplete<In: Into<String> + Send>(
         &self,
         input: In,
-    ) -> Result<Vec<crate::generic::AutoCompleteItem>, reqwest::Error> {
-        self.builder
-            .client
-            .get(format!("{}/index.php", self.builder.url))
-            .query(&[
-                ("limit", self.query.limit.to_string().as_str()),
-                ("page", "autocomplete2"),
-                ("type", "tag_query"),
-                ("term", &input.into()),
-            ])
-            .send()
-            .await?
-            .json::<Vec<AutoCompleteItem>>()
-            .await
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        self.traced("get_autocomplete", async move {
+            self.builder
+                .send(
+                    HttpRequest::get(format!("{}/index.php", self.builder.url))
+                        .query([
+                            ("limit", self.query.limit.to_string().as_str()),
+                            ("page", "autocomplete2"),
+                            ("type", "tag_query"),
+                            ("term", &input.into()),
+                        ])
+                        .endpoint(Endpoint::Autocomplete),
+                )
+                .await?
+                .error_for_status()?
+                .json_or_default()
+        })
+        .await
     }
 
     async fn get_by_id(&self, id: u32) -> Result<Option<GelbooruPost>, shared::Error> {
-        self.builder
-            .client
-            .get(format!("{}/index.php", &self.builder.url))
-            .query(&Self::get_query(QueryMode::Single(id)))
-            .send()
-            .await?
-            .json::<GelbooruResponse>()
-            .await
-            .map(|r| r.posts.into_iter().next())
-            .map_err(Into::into)
+        self.traced("get_by_id", async move {
+            self.builder
+                .send(
+                    HttpRequest::get(format!("{}/index.php", &self.builder.url))
+                        .query(Self::get_query(QueryMode::Single(id)))
+                        .endpoint(Endpoint::Post),
+                )
+                .await?
+                .error_for_status()?
+                .json_or_default::<GelbooruResponse>()
+                .map(|r| r.posts.into_iter().next())
+        })
+        .await
     }
 
     async fn get(&self) -> Result<Vec<GelbooruPost>, shared::Error> {
-        self.builder
-            .client
-            .get(format!("{}/index.php", &self.builder.url))
-            .query(&Self::get_query(QueryMode::Multiple(&self.query)))
-            .send()
-            .await?
-            .json::<GelbooruResponse>()
-            .await
-            .map(|r| r.posts)
-            .map_err(Into::into)
+        self.traced("get", async move {
+            self.builder
+                .send(
+                    HttpRequest::get(format!("{}/index.php", &self.builder.url))
+                        .query(Self::get_query(QueryMode::Multiple(&self.query)))
+                        .endpoint(Endpoint::Posts),
+                )
+                .await?
+                .error_for_status()?
+                .json_or_default::<GelbooruResponse>()
+                .map(|r| r.posts)
+        })
+        .await
     }
 }
@@ -2,41 +2,64 @@ pub mod client;
 
 use derive_more::From;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use strum::Display;
 
-use crate::generic::{BooruPost, Rating};
+use crate::{
+    generic::{BooruPost, Rating},
+    shared::{
+        interner::{InternTags, Interned, InternedTags, TagInterner},
+        lenient,
+        pages::WithId,
+    },
+};
 
 /// Individual post from [`GelbooruResponse`]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct GelbooruPost {
     /// The ID of the post
+    #[serde(deserialize_with = "lenient::required_number")]
     pub id: u32,
     /// Datestamp of the post's creating date
+    #[serde(default, deserialize_with = "lenient::string")]
     pub created_at: String,
-    /// Post's score
-    pub score: u32,
+    /// Post's score, which can be negative
+    #[serde(default, deserialize_with = "lenient::number")]
+    pub score: i64,
     /// Post's image width
+    #[serde(default, deserialize_with = "lenient::number")]
     pub width: u32,
     /// Post's image height
+    #[serde(default, deserialize_with = "lenient::number")]
     pub height: u32,
     /// Post's image md5
+    #[serde(default, deserialize_with = "lenient::string")]
     pub md5: String,
     /// Post's image file url
+    #[serde(default, deserialize_with = "lenient::string")]
     pub file_url: String,
     /// Post's tags
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tags: String,
     /// Post's image name (with extension)
+    #[serde(default, deserialize_with = "lenient::string")]
     pub image: String,
     /// Post's image source
+    #[serde(default, deserialize_with = "lenient::string")]
     pub source: String,
     /// Post's rating
-    pub rating: GelbooruRating,
+    #[serde(default, deserialize_with = "lenient::known")]
+    pub rating: Option<GelbooruRating>,
+    /// Any field that isn't modeled above, kept as sent by the API.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Gelbooru's API response with a list a posts
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct GelbooruResponse {
-    #[serde(rename = "post")]
+    /// Missing altogether when the search has no results
+    #[serde(rename = "post", default)]
     pub posts: Vec<GelbooruPost>,
 }
 
@@ -80,16 +103,36 @@ impl From<GelbooruPost> for BooruPost {
     fn from(post: GelbooruPost) -> Self {
         BooruPost {
             id: post.id,
-            created_at: post.created_at.into(),
-            score: post.score.into(),
+            created_at: present(post.created_at),
+            score: post.score,
             width: post.width,
             height: post.height,
-            md5: post.md5.into(),
-            file_url: post.file_url.into(),
+            md5: present(post.md5),
+            file_url: present(post.file_url),
             tags: post.tags,
-            image: post.image.into(),
-            source: post.source.into(),
-            rating: post.rating.into(),
+            image: present(post.image),
+            source: present(post.source),
+            rating: post.rating.map(Into::into),
         }
     }
 }
+
+/// Fields missing from the API are read as empty strings, which posts of any booru leave unset.
+fn present(field: String) -> Option<String> {
+    Some(field).filter(|field| !field.is_empty())
+}
+
+impl WithId for GelbooruPost {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl InternTags for GelbooruPost {
+    type Tags = InternedTags;
+
+    fn intern_tags(mut self, interner: &TagInterner) -> Interned<Self> {
+        let tags = interner.intern_all(&std::mem::take(&mut self.tags));
+        Interned { post: self, tags }
+    }
+}
@@ -0,0 +1,133 @@
+//! Boorus as trait objects.
+//!
+//! [`QueryDispatcher`] is generic over its client, which keeps every backend statically typed but
+//! rules out choosing one at runtime. [`Booru`] is the same set of calls over [`BooruPost`], boxed
+//! so it can live behind a `dyn`. Every [`ClientBuilder`] implements it, and applications can
+//! implement it for their own backends and hand them to a [`BooruRegistry`]:
+//!
+//! ```ignore
+//! let client = GenericClient::new();
+//! client.registry.register("mybooru", MyBooru::new());
+//!
+//! let posts = client.get(&query, BooruOption::Custom("mybooru".into())).await?;
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::shared::{
+    client::{
+        ClientBuilder, ClientInformation, ClientQueryBuilder, ClientQueryDispatcher, ClientTypes,
+        QueryDispatcher,
+    },
+    transport::BoxFuture,
+    Error,
+};
+
+use super::{client::GenericClient, AutoCompleteItem, BooruPost};
+
+/// A booru queried with generic queries, returning generic posts.
+pub trait Booru: Send + Sync {
+    /// Name used in logs and errors.
+    fn name(&self) -> &str;
+
+    fn get<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+    ) -> BoxFuture<'a, Result<Vec<BooruPost>, Error>>;
+
+    fn get_by_id(&self, id: u32) -> BoxFuture<'_, Result<Option<BooruPost>, Error>>;
+
+    fn get_autocomplete<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+        input: String,
+    ) -> BoxFuture<'a, Result<Vec<AutoCompleteItem>, Error>>;
+}
+
+impl<T: ClientTypes + ClientInformation + Clone + Send + Sync> Booru for ClientBuilder<T>
+where
+    ClientQueryDispatcher<T>: QueryDispatcher<T>,
+    T::Rating: Send + Sync,
+    T::Post: Into<BooruPost> + Send,
+{
+    fn name(&self) -> &str {
+        T::NAME
+    }
+
+    fn get<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+    ) -> BoxFuture<'a, Result<Vec<BooruPost>, Error>> {
+        let dispatcher = self.query_raw(&mut query.convert());
+
+        Box::pin(async move {
+            dispatcher
+                .get()
+                .await
+                .map(|v| v.into_iter().map(Into::into).collect())
+        })
+    }
+
+    fn get_by_id(&self, id: u32) -> BoxFuture<'_, Result<Option<BooruPost>, Error>> {
+        let dispatcher = self.dispatch();
+
+        Box::pin(async move { dispatcher.get_by_id(id).await.map(|v| v.map(Into::into)) })
+    }
+
+    fn get_autocomplete<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+        input: String,
+    ) -> BoxFuture<'a, Result<Vec<AutoCompleteItem>, Error>> {
+        let dispatcher = self.query_raw(&mut query.convert());
+
+        Box::pin(async move { dispatcher.get_autocomplete(input).await })
+    }
+}
+
+/// Boorus registered by name, reached through
+/// [`BooruOption::Custom`](super::client::BooruOption::Custom).
+///
+/// Clones share their entries, so a booru registered on one is visible from all of them,
+/// including the registry of [`GenericClient::shared`] whatever runtime it was taken from.
+#[derive(Clone, Default)]
+pub struct BooruRegistry(Arc<RwLock<HashMap<String, Arc<dyn Booru>>>>);
+
+impl std::fmt::Debug for BooruRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+impl BooruRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `booru` under `name`, replacing whatever was registered under it before.
+    pub fn register(&self, name: impl Into<String>, booru: impl Booru + 'static) {
+        self.register_arc(name, Arc::new(booru));
+    }
+
+    pub fn register_arc(&self, name: impl Into<String>, booru: Arc<dyn Booru>) {
+        self.0.write().unwrap().insert(name.into(), booru);
+    }
+
+    pub fn unregister(&self, name: &str) -> Option<Arc<dyn Booru>> {
+        self.0.write().unwrap().remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Booru>> {
+        self.0.read().unwrap().get(name).cloned()
+    }
+
+    /// Names of the registered boorus, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.0.read().unwrap().keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
@@ -1,35 +1,96 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+};
+
 use strum::EnumIter;
 
 use crate::{
-    danbooru::client::DanbooruClient,
-    gelbooru::client::GelbooruClient,
     generic::AutoCompleteItem,
-    safebooru::client::SafebooruClient,
     shared::{
         self,
-        client::{
-            ClientInformation, ClientQueryBuilder, ClientQueryDispatcher, ClientTypes,
-            QueryDispatcher, WithClientBuilder,
-        },
+        client::{ClientInformation, ClientQueryBuilder, ClientTypes},
         Tag,
     },
 };
 
-use super::{BooruPost, Rating};
+use super::{
+    booru::{Booru, BooruRegistry},
+    tags::TagMapping,
+    BooruPost, Rating,
+};
+
+#[cfg(any(feature = "danbooru", feature = "gelbooru", feature = "safebooru"))]
+use crate::shared::client::{ClientBuilder, WithClientBuilder};
+
+#[cfg(feature = "danbooru")]
+use crate::danbooru::{
+    client::DanbooruClient,
+    instance::{Instance, InstanceConfig},
+};
+#[cfg(feature = "gelbooru")]
+use crate::gelbooru::client::GelbooruClient;
+#[cfg(feature = "safebooru")]
+use crate::safebooru::client::SafebooruClient;
+#[cfg(feature = "sites")]
+use crate::site::{client::Site, SiteConfig};
 
+/// Client able to send the same query to any of the supported boorus.
+///
+/// It keeps one [`ClientBuilder`] per booru, so connections are reused across calls and each
+/// booru can be configured on its own, e.g. to point it at a mirror or give it an API key.
 #[derive(Debug, Clone)]
-pub struct GenericClient();
+pub struct GenericClient {
+    #[cfg(feature = "gelbooru")]
+    pub gelbooru: ClientBuilder<GelbooruClient>,
+    #[cfg(feature = "safebooru")]
+    pub safebooru: ClientBuilder<SafebooruClient>,
+    #[cfg(feature = "danbooru")]
+    pub danbooru: ClientBuilder<DanbooruClient>,
+    /// Boorus reached through [`BooruOption::Custom`].
+    pub registry: BooruRegistry,
+    /// Names tags are translated with, see [`GenericClient::tag_mapping`]. Clones share it.
+    pub mapping: Arc<RwLock<Option<Arc<dyn TagMapping>>>>,
+}
+
+impl Default for GenericClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl ClientTypes for GenericClient {
     type Rating = Rating;
     type Post = BooruPost;
 }
 
-#[derive(EnumIter)]
+/// Which booru to send a query to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
 pub enum BooruOption {
+    #[cfg(feature = "gelbooru")]
     Gelbooru,
+    #[cfg(feature = "safebooru")]
     Safebooru,
+    #[cfg(feature = "danbooru")]
     Danbooru,
+    /// A booru of the [`GenericClient::registry`], by the name it was registered under.
+    #[strum(disabled)]
+    Custom(String),
+}
+
+impl BooruOption {
+    /// Name of the booru, the one it was registered under for [`BooruOption::Custom`].
+    pub fn name(&self) -> &str {
+        match self {
+            #[cfg(feature = "gelbooru")]
+            BooruOption::Gelbooru => "gelbooru",
+            #[cfg(feature = "safebooru")]
+            BooruOption::Safebooru => "safebooru",
+            #[cfg(feature = "danbooru")]
+            BooruOption::Danbooru => "danbooru",
+            BooruOption::Custom(name) => name,
+        }
+    }
 }
 
 impl<T: ClientTypes> From<&Tag<GenericClient>> for Tag<T> {
@@ -43,58 +104,167 @@ impl<T: ClientTypes> From<&Tag<GenericClient>> for Tag<T> {
     }
 }
 
-macro_rules! handle_request {
-    (@ $t:ident, ($($args:expr),*), ($($gen:ty),*)) => {
-        request::<$t, $($gen,)*>($($args,)*).await
-    };
+impl ClientQueryBuilder<GenericClient> {
+    pub(crate) fn convert<T: ClientTypes + ClientInformation + Clone>(
+        &self,
+    ) -> ClientQueryBuilder<T> {
+        let mut query = ClientQueryBuilder::new();
 
-    ($booru_option:expr, ($($args:expr),*), ($($gen:ty),*)) => {
-        match $booru_option {
-            BooruOption::Gelbooru => handle_request!(@ GelbooruClient, ($($args),*), ($($gen),*)),
-            BooruOption::Safebooru => handle_request!(@ SafebooruClient, ($($args),*), ($($gen),*)),
-            BooruOption::Danbooru => handle_request!(@ DanbooruClient, ($($args),*), ($($gen),*)),
+        for tag in self.tags.0.iter() {
+            query.any_tag(tag.into());
         }
-    };
 
-    ($booru_option:expr, ($($args:expr),*)) => {
-        handle_request!($booru_option, ($($args),*), ())
+        query.limit = self.limit;
+        query.page = self.page;
+        query
+    }
+
+    /// Same as [`GenericClient::get_autocomplete`], through [`GenericClient::shared`].
+    pub async fn get_autocomplete<In: Into<String> + Send>(
+        &self,
+        booru: BooruOption,
+        input: In,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        GenericClient::shared()
+            .get_autocomplete(self, booru, input)
+            .await
+    }
+
+    /// Same as [`GenericClient::get_by_id`], through [`GenericClient::shared`].
+    pub async fn get_by_id(
+        &self,
+        id: u32,
+        booru: BooruOption,
+    ) -> Result<Option<BooruPost>, shared::Error> {
+        GenericClient::shared().get_by_id(id, booru).await
+    }
+
+    /// Same as [`GenericClient::get`], through [`GenericClient::shared`].
+    pub async fn get(&self, booru: BooruOption) -> Result<Vec<BooruPost>, shared::Error> {
+        GenericClient::shared().get(self, booru).await
     }
 }
 
-impl ClientQueryBuilder<GenericClient> {
-    fn convert<T: ClientTypes + ClientInformation + Clone>(&self) -> ClientQueryBuilder<T> {
-        let mut query = ClientQueryBuilder::new();
+impl GenericClient {
+    /// Client with the default builder of every booru.
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "gelbooru")]
+            gelbooru: GelbooruClient::builder(),
+            #[cfg(feature = "safebooru")]
+            safebooru: SafebooruClient::builder(),
+            #[cfg(feature = "danbooru")]
+            danbooru: DanbooruClient::builder(),
+            registry: BooruRegistry::new(),
+            mapping: Arc::default(),
+        }
+    }
 
-        for tag in self.tags.0.iter() {
-            query.tag::<Tag<T>>(tag.into());
+    /// Client used by the methods of [`ClientQueryBuilder<GenericClient>`].
+    ///
+    /// Connections can't be reused from one tokio runtime to the next, so every runtime gets a
+    /// client of its own, created the first time it asks for one with the
+    /// [`HttpOptions::global`](crate::shared::transport::HttpOptions::global) of that moment.
+    /// The client of a runtime is dropped along with the runtime. They all share the same
+    /// [`GenericClient::registry`] and [`GenericClient::tag_mapping`], though boorus registered
+    /// there keep whatever connections they hold.
+    pub fn shared() -> GenericClient {
+        let runtime = runtime_id();
+
+        let mut shared = SharedClients::get().lock().unwrap();
+        if let Some(client) = shared.clients.get(&runtime) {
+            return client.clone();
         }
 
-        query
+        let mut client = GenericClient::new();
+        client.registry = shared.registry.clone();
+        client.mapping = shared.mapping.clone();
+        shared.clients.insert(runtime, client.clone());
+
+        // The runtime may already be shutting down, in which case the client is evicted right
+        // away, so the lock must be released first.
+        drop(shared);
+        evict_with_runtime(runtime);
+        client
+    }
+
+    pub fn query() -> ClientQueryBuilder<GenericClient> {
+        ClientQueryBuilder::new()
+    }
+
+    /// Registers `booru` in [`GenericClient::registry`], see [`BooruRegistry::register`].
+    pub fn register(&self, name: impl Into<String>, booru: impl Booru + 'static) -> &Self {
+        self.registry.register(name, booru);
+        self
+    }
+
+    /// Registers the site described by `config` under its name, see [`Site`].
+    #[cfg(feature = "sites")]
+    pub fn register_site(&self, config: SiteConfig) -> &Self {
+        self.register(config.name.clone(), Site::new(config))
+    }
+
+    /// Registers the Danbooru instance described by `config` under its name, see
+    /// [`Instance`].
+    #[cfg(feature = "danbooru")]
+    pub fn register_instance(&self, config: InstanceConfig) -> &Self {
+        self.register(config.name.clone(), Instance::new(config))
+    }
+
+    /// Translates the tags of queries into the names each booru gives them, and the tags of the
+    /// posts and completions they answer with back into canonical names.
+    ///
+    /// The mapping is shared with the clones of this client, so setting it on
+    /// [`GenericClient::shared`] applies it to [`ClientQueryBuilder::search`] as well.
+    pub fn tag_mapping(&self, mapping: impl TagMapping + 'static) -> &Self {
+        *self.mapping.write().unwrap() = Some(Arc::new(mapping));
+        self
+    }
+
+    /// The mapping set by [`GenericClient::tag_mapping`], if any.
+    fn current_mapping(&self) -> Option<Arc<dyn TagMapping>> {
+        self.mapping.read().unwrap().clone()
+    }
+
+    /// The booru `booru` refers to.
+    pub fn booru(&self, booru: &BooruOption) -> Result<Arc<dyn Booru>, shared::Error> {
+        Ok(match booru {
+            #[cfg(feature = "gelbooru")]
+            BooruOption::Gelbooru => Arc::new(self.gelbooru.clone()),
+            #[cfg(feature = "safebooru")]
+            BooruOption::Safebooru => Arc::new(self.safebooru.clone()),
+            #[cfg(feature = "danbooru")]
+            BooruOption::Danbooru => Arc::new(self.danbooru.clone()),
+            BooruOption::Custom(name) => self
+                .registry
+                .get(name)
+                .ok_or_else(|| shared::Error::UnknownBooru(name.clone()))?,
+        })
     }
 
     pub async fn get_autocomplete<In: Into<String> + Send>(
         &self,
+        query: &ClientQueryBuilder<GenericClient>,
         booru: BooruOption,
         input: In,
-    ) -> Result<Vec<AutoCompleteItem>, reqwest::Error> {
-        async fn request<
-            T: ClientTypes + ClientInformation + WithClientBuilder<T> + Clone,
-            In: Into<String> + Send,
-        >(
-            query: &ClientQueryBuilder<GenericClient>,
-            input: In,
-        ) -> Result<Vec<AutoCompleteItem>, reqwest::Error>
-        where
-            ClientQueryDispatcher<T>: QueryDispatcher<T>,
-        {
-            T::builder()
-                .query_raw(&mut query.convert())
-                .get_autocomplete(input)
-                .await
-                .map_err(Into::into)
-        }
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        let Some(mapping) = self.current_mapping() else {
+            return self
+                .booru(&booru)?
+                .get_autocomplete(query, input.into())
+                .await;
+        };
 
-        handle_request!(booru, (self, input), (In))
+        let query = query.translate(&*mapping, booru.name());
+        let mut items = self
+            .booru(&booru)?
+            .get_autocomplete(&query, input.into())
+            .await?;
+
+        for item in items.iter_mut() {
+            item.normalize(&*mapping, booru.name());
+        }
+        Ok(items)
     }
 
     pub async fn get_by_id(
@@ -102,45 +272,93 @@ impl ClientQueryBuilder<GenericClient> {
         id: u32,
         booru: BooruOption,
     ) -> Result<Option<BooruPost>, shared::Error> {
-        async fn request<T: ClientTypes + ClientInformation + WithClientBuilder<T> + Clone>(
-            query: &ClientQueryBuilder<GenericClient>,
-            id: u32,
-        ) -> Result<Option<BooruPost>, shared::Error>
-        where
-            ClientQueryDispatcher<T>: QueryDispatcher<T>,
-        {
-            T::builder()
-                .query_raw(&mut query.convert())
-                .get_by_id(id)
-                .await
-                .map(|v| v.map(Into::into))
-                .map_err(Into::into)
-        }
+        let mut post = self.booru(&booru)?.get_by_id(id).await?;
 
-        handle_request!(booru, (self, id))
+        if let (Some(mapping), Some(post)) = (self.current_mapping(), &mut post) {
+            post.normalize(&*mapping, booru.name());
+        }
+        Ok(post)
     }
 
-    pub async fn get(&self, booru: BooruOption) -> Result<Vec<BooruPost>, shared::Error> {
-        async fn request<T: ClientTypes + ClientInformation + WithClientBuilder<T> + Clone>(
-            query: &ClientQueryBuilder<GenericClient>,
-        ) -> Result<Vec<BooruPost>, shared::Error>
-        where
-            ClientQueryDispatcher<T>: QueryDispatcher<T>,
-        {
-            T::builder()
-                .query_raw(&mut query.convert())
-                .get()
-                .await
-                .map_err(Into::into)
-                .map(|v| v.into_iter().map(Into::into).collect())
+    /// Searches `booru` with `query`, whose limit and page are passed on as they are.
+    pub async fn get(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+        booru: BooruOption,
+    ) -> Result<Vec<BooruPost>, shared::Error> {
+        let Some(mapping) = self.current_mapping() else {
+            return self.booru(&booru)?.get(query).await;
+        };
+
+        let query = query.translate(&*mapping, booru.name());
+        let mut posts = self.booru(&booru)?.get(&query).await?;
+
+        for post in posts.iter_mut() {
+            post.normalize(&*mapping, booru.name());
         }
+        Ok(posts)
+    }
+}
 
-        handle_request!(booru, (self))
+/// What [`GenericClient::shared`] hands out, by runtime.
+#[derive(Default)]
+struct SharedClients {
+    registry: BooruRegistry,
+    mapping: Arc<RwLock<Option<Arc<dyn TagMapping>>>>,
+    clients: HashMap<Option<RuntimeId>, GenericClient>,
+}
+
+impl SharedClients {
+    fn get() -> &'static Mutex<SharedClients> {
+        static SHARED: OnceLock<Mutex<SharedClients>> = OnceLock::new();
+        SHARED.get_or_init(Default::default)
     }
 }
 
-impl GenericClient {
-    pub fn query() -> ClientQueryBuilder<GenericClient> {
-        ClientQueryBuilder::new()
+/// Drops the shared client of a runtime when the runtime drops the task holding it, which it
+/// does when shutting down.
+#[cfg(feature = "tokio")]
+struct Evict(RuntimeId);
+
+#[cfg(feature = "tokio")]
+impl Drop for Evict {
+    fn drop(&mut self) {
+        let client = SharedClients::get()
+            .lock()
+            .unwrap()
+            .clients
+            .remove(&Some(self.0));
+        drop(client);
     }
 }
+
+/// Drops the shared client of `runtime` once the runtime is gone.
+fn evict_with_runtime(runtime: Option<RuntimeId>) {
+    #[cfg(feature = "tokio")]
+    if let Some(runtime) = runtime {
+        tokio::spawn(async move {
+            let _evict = Evict(runtime);
+            std::future::pending::<()>().await
+        });
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    let _ = runtime;
+}
+
+#[cfg(feature = "tokio")]
+type RuntimeId = tokio::runtime::Id;
+
+#[cfg(not(feature = "tokio"))]
+type RuntimeId = ();
+
+/// The tokio runtime the caller runs on, if any.
+fn runtime_id() -> Option<RuntimeId> {
+    #[cfg(feature = "tokio")]
+    return tokio::runtime::Handle::try_current()
+        .ok()
+        .map(|handle| handle.id());
+
+    #[cfg(not(feature = "tokio"))]
+    None
+}
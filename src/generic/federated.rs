@@ -0,0 +1,289 @@
+//! The same search sent to several boorus at once.
+//!
+//! Every booru is queried concurrently and given its own deadline, so a slow or failing one only
+//! costs its own results. Posts are then merged in the chosen [`Order`] and those found on more
+//! than one booru, recognized by their md5, are only kept once.
+
+use std::{cmp::Reverse, collections::HashSet, sync::Arc, time::Duration};
+
+use futures_util::future::join_all;
+
+use crate::shared::{
+    client::ClientQueryBuilder,
+    runtime::{self, default_runtime, Runtime},
+    Error,
+};
+
+use super::{
+    client::{BooruOption, GenericClient},
+    BooruPost,
+};
+
+/// How the posts of the different boorus are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// One post of each booru in turn, in the order the boorus were given. Each booru keeps the
+    /// order it answered with.
+    #[default]
+    Interleave,
+    /// Highest score first.
+    Score,
+    /// Newest first. Posts without a date the crate can read come last.
+    Date,
+}
+
+/// Settings of a federated search.
+#[derive(Clone)]
+pub struct Federation {
+    pub order: Order,
+    /// How long each booru has to answer, `None` waits as long as they take.
+    pub timeout: Option<Duration>,
+    /// Runtime measuring the timeouts.
+    pub runtime: Arc<dyn Runtime>,
+}
+
+impl std::fmt::Debug for Federation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Federation")
+            .field("order", &self.order)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Federation {
+    fn default() -> Self {
+        Self {
+            order: Order::default(),
+            timeout: Some(Duration::from_secs(30)),
+            runtime: default_runtime(),
+        }
+    }
+}
+
+impl Federation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn runtime(mut self, runtime: impl Runtime + 'static) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+}
+
+/// A post along with the booru it was found on.
+#[derive(Debug, Clone)]
+pub struct SourcedPost {
+    pub booru: BooruOption,
+    pub post: BooruPost,
+}
+
+/// What [`GenericClient::search`] found.
+#[derive(Debug)]
+pub struct Federated {
+    /// Posts of every booru that answered, merged and without duplicates.
+    pub posts: Vec<SourcedPost>,
+    /// Boorus that failed or timed out, with what went wrong.
+    pub failures: Vec<(BooruOption, Error)>,
+}
+
+impl Federated {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl ClientQueryBuilder<GenericClient> {
+    /// Same as [`GenericClient::search`], through [`GenericClient::shared`].
+    pub async fn search(
+        &self,
+        boorus: impl IntoIterator<Item = BooruOption>,
+        federation: &Federation,
+    ) -> Federated {
+        GenericClient::shared()
+            .search(self, boorus, federation)
+            .await
+    }
+}
+
+impl GenericClient {
+    /// Sends `query` to every booru of `boorus` concurrently and merges what they found.
+    pub async fn search(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+        boorus: impl IntoIterator<Item = BooruOption>,
+        federation: &Federation,
+    ) -> Federated {
+        let searches = boorus.into_iter().map(|booru| async move {
+            let search = self.get(query, booru.clone());
+
+            let result = match federation.timeout {
+                Some(timeout) => runtime::timeout(&*federation.runtime, timeout, search)
+                    .await
+                    .unwrap_or(Err(Error::Timeout(timeout))),
+                None => search.await,
+            };
+            (booru, result)
+        });
+
+        let mut found = Vec::new();
+        let mut failures = Vec::new();
+
+        for (booru, result) in join_all(searches).await {
+            match result {
+                Ok(posts) => found.push((booru, posts)),
+                Err(e) => failures.push((booru, e)),
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let posts = merge(found, federation.order)
+            .into_iter()
+            .filter(|sourced| match &sourced.post.md5 {
+                Some(md5) => seen.insert(md5.to_lowercase()),
+                None => true,
+            })
+            .collect();
+
+        Federated { posts, failures }
+    }
+}
+
+fn merge(found: Vec<(BooruOption, Vec<BooruPost>)>, order: Order) -> Vec<SourcedPost> {
+    let mut lists = found
+        .into_iter()
+        .map(|(booru, posts)| {
+            posts.into_iter().map(move |post| SourcedPost {
+                booru: booru.clone(),
+                post,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut posts = Vec::new();
+
+    // Round robin first, so ties in the sorts below keep the boorus alternating.
+    loop {
+        let before = posts.len();
+        posts.extend(lists.iter_mut().filter_map(Iterator::next));
+        if posts.len() == before {
+            break;
+        }
+    }
+
+    match order {
+        Order::Interleave => {}
+        Order::Score => posts.sort_by_key(|sourced| Reverse(sourced.post.score)),
+        Order::Date => posts.sort_by_cached_key(|sourced| {
+            Reverse(sourced.post.created_at.as_deref().and_then(timestamp))
+        }),
+    }
+
+    posts
+}
+
+/// Seconds since the epoch of a post's `created_at`. Boorus either send ISO 8601 like Danbooru
+/// (`2024-01-31T12:00:00.000-05:00`) or a date like Gelbooru (`Wed Jan 31 12:00:00 -0500 2024`).
+/// Dates that don't exist, like the 30th of February, are `None`.
+pub(crate) fn timestamp(date: &str) -> Option<i64> {
+    let date = date.trim();
+
+    if let Some((day, time)) = date.split_once(['T', ' ']).filter(|(d, _)| d.len() == 10) {
+        let mut ymd = day.splitn(3, '-').map(str::parse::<i64>);
+        let (year, month, day) = (ymd.next()?.ok()?, ymd.next()?.ok()?, ymd.next()?.ok()?);
+
+        let split = time.find(['Z', '+', '-']).unwrap_or(time.len());
+        let (clock, zone) = time.split_at(split);
+        let clock = clock.split('.').next()?;
+        let zone = offset(zone.trim_start_matches('Z'))?;
+
+        return Some(epoch(year, month, day)? + seconds_of_day(clock)? - zone);
+    }
+
+    let parts = date.split_whitespace().collect::<Vec<_>>();
+    let [_, month, day, clock, zone, year] = parts[..] else {
+        return None;
+    };
+
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+
+    let (year, day) = (year.parse().ok()?, day.parse().ok()?);
+
+    Some(epoch(year, month, day)? + seconds_of_day(clock)? - offset(zone)?)
+}
+
+fn seconds_of_day(clock: &str) -> Option<i64> {
+    let mut parts = clock.splitn(3, ':').map(str::parse::<i64>);
+    let (h, m) = (parts.next()?.ok()?, parts.next()?.ok()?);
+    let s = parts.next().transpose().ok()?.unwrap_or(0);
+
+    // 60 seconds for leap seconds.
+    let valid = (0..24).contains(&h) && (0..60).contains(&m) && (0..=60).contains(&s);
+    valid.then_some(h * 3600 + m * 60 + s)
+}
+
+/// Seconds east of UTC of `+hh:mm`, `+hhmm` or nothing at all.
+fn offset(zone: &str) -> Option<i64> {
+    if zone.is_empty() {
+        return Some(0);
+    }
+
+    let (sign, digits) = if let Some(digits) = zone.strip_prefix('+') {
+        (1, digits)
+    } else {
+        (-1, zone.strip_prefix('-')?)
+    };
+    // Checked before slicing, which anything but ASCII digits could split inside a character.
+    let digits = digits.replace(':', "");
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let (hours, minutes) = (
+        digits[..2].parse::<i64>().ok()?,
+        digits[2..].parse::<i64>().ok()?,
+    );
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Seconds since the epoch of the start of a UTC day, with the days counted as in Howard
+/// Hinnant's `days_from_civil`. `None` when there is no such day.
+fn epoch(year: i64, month: i64, day: i64) -> Option<i64> {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return None;
+    }
+
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    Some((era * 146097 + day_of_era - 719468) * 86400)
+}
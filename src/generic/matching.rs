@@ -0,0 +1,325 @@
+//! Finding a post again on other boorus.
+//!
+//! Each booru is searched with what identifies the post best and falls back to weaker evidence
+//! only when that found nothing:
+//!
+//! 1. its md5, which finds the very same file,
+//! 2. its source, which finds uploads of the same artwork, possibly resized or recompressed,
+//! 3. its most specific tags, keeping the posts of the same dimensions that share enough tags.
+//!
+//! Every post found is returned as a [`Link`] with the [`Confidence`] of the step that found it.
+
+use std::{cmp::Reverse, collections::HashSet, sync::Arc, time::Duration};
+
+use futures_util::future::join_all;
+
+use crate::shared::{
+    runtime::{self, default_runtime, Runtime},
+    Error,
+};
+
+use super::{
+    client::{BooruOption, GenericClient},
+    federated::SourcedPost,
+    BooruPost,
+};
+
+/// How sure a [`Link`] is to point at the same artwork.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// Same dimensions and enough tags in common. Edits and other pages of a set look the same.
+    Low,
+    /// Same source, though the file may have been resized or recompressed.
+    High,
+    /// Same md5, so the same file.
+    Certain,
+}
+
+/// Settings of a search for matching posts.
+#[derive(Clone)]
+pub struct Matcher {
+    /// Least share of tags two posts must have in common to be matched on their tags, from 0 to 1.
+    pub overlap: f32,
+    /// How many tags the last step searches with. Danbooru doesn't allow more than 2 without an
+    /// account.
+    pub tags: usize,
+    /// How many posts each step asks for.
+    pub candidates: u32,
+    /// How long each booru has to answer all the steps together, `None` waits as long as they
+    /// take.
+    pub timeout: Option<Duration>,
+    /// Runtime measuring the timeouts.
+    pub runtime: Arc<dyn Runtime>,
+}
+
+impl std::fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Matcher")
+            .field("overlap", &self.overlap)
+            .field("tags", &self.tags)
+            .field("candidates", &self.candidates)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self {
+            overlap: 0.5,
+            tags: 2,
+            candidates: 20,
+            timeout: Some(Duration::from_secs(30)),
+            runtime: default_runtime(),
+        }
+    }
+}
+
+impl Matcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn overlap(mut self, overlap: f32) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn tags(mut self, tags: usize) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn candidates(mut self, candidates: u32) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn runtime(mut self, runtime: impl Runtime + 'static) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+}
+
+/// A post found to be the same artwork as the one matched.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub post: SourcedPost,
+    pub confidence: Confidence,
+    /// Share of tags both posts have in common, see [`overlap`].
+    pub overlap: f32,
+}
+
+/// What [`GenericClient::find_matches`] found.
+#[derive(Debug)]
+pub struct Matches {
+    /// The post that was matched.
+    pub origin: SourcedPost,
+    /// Posts of the other boorus, most confident first.
+    pub links: Vec<Link>,
+    /// Boorus that failed or timed out, with what went wrong.
+    pub failures: Vec<(BooruOption, Error)>,
+}
+
+impl Matches {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Links at least as confident as `confidence`.
+    pub fn at_least(&self, confidence: Confidence) -> impl Iterator<Item = &Link> {
+        self.links
+            .iter()
+            .filter(move |link| link.confidence >= confidence)
+    }
+}
+
+impl SourcedPost {
+    /// Same as [`GenericClient::find_matches`], through [`GenericClient::shared`].
+    pub async fn find_matches(
+        &self,
+        boorus: impl IntoIterator<Item = BooruOption>,
+        matcher: &Matcher,
+    ) -> Matches {
+        GenericClient::shared()
+            .find_matches(self, boorus, matcher)
+            .await
+    }
+}
+
+impl GenericClient {
+    /// Looks for `origin` on every booru of `boorus` concurrently. The origin itself is left out
+    /// when its own booru is searched, other posts of it are kept since boorus have duplicates.
+    pub async fn find_matches(
+        &self,
+        origin: &SourcedPost,
+        boorus: impl IntoIterator<Item = BooruOption>,
+        matcher: &Matcher,
+    ) -> Matches {
+        let searches = boorus.into_iter().map(|booru| async move {
+            let search = self.match_on(&origin.post, &booru, matcher);
+
+            let result = match matcher.timeout {
+                Some(timeout) => runtime::timeout(&*matcher.runtime, timeout, search)
+                    .await
+                    .unwrap_or(Err(Error::Timeout(timeout))),
+                None => search.await,
+            };
+            (booru, result)
+        });
+
+        let mut links = Vec::new();
+        let mut failures = Vec::new();
+
+        for (booru, result) in join_all(searches).await {
+            match result {
+                Ok(found) => links.extend(
+                    found
+                        .into_iter()
+                        .filter(|(post, _)| booru != origin.booru || post.id != origin.post.id)
+                        .map(|(post, confidence)| Link {
+                            overlap: overlap(&origin.post.tags, &post.tags),
+                            post: SourcedPost {
+                                booru: booru.clone(),
+                                post,
+                            },
+                            confidence,
+                        }),
+                ),
+                Err(e) => failures.push((booru, e)),
+            }
+        }
+
+        links.sort_by_key(|link| Reverse(link.confidence));
+
+        Matches {
+            origin: origin.clone(),
+            links,
+            failures,
+        }
+    }
+
+    /// Posts of `booru` matching `origin`, found by the first step that found any.
+    async fn match_on(
+        &self,
+        origin: &BooruPost,
+        booru: &BooruOption,
+        matcher: &Matcher,
+    ) -> Result<Vec<(BooruPost, Confidence)>, Error> {
+        // Through `get`, so the tags are translated and compared by their canonical names.
+        let search = |tags: Vec<String>| {
+            let mut query = GenericClient::query();
+            query.limit(matcher.candidates);
+            for tag in tags {
+                query.tag(tag);
+            }
+            async move { self.get(&query, booru.clone()).await }
+        };
+
+        // Posts without an md5 can't be told to be the same file, the next steps may still find
+        // them.
+        if let Some(md5) = origin.md5.as_deref().filter(|md5| !md5.is_empty()) {
+            let found = search(vec![format!("md5:{md5}")])
+                .await?
+                .into_iter()
+                .filter(|post| {
+                    post.md5
+                        .as_deref()
+                        .is_some_and(|other| other.eq_ignore_ascii_case(md5))
+                })
+                .map(|post| (post, Confidence::Certain))
+                .collect::<Vec<_>>();
+
+            if !found.is_empty() {
+                return Ok(found);
+            }
+        }
+
+        // Some boorus hold several urls in the source, which can't be searched as one tag. The
+        // first one is searched for, and kept are the posts listing it among theirs.
+        let source = origin
+            .source
+            .as_deref()
+            .and_then(|s| s.split_whitespace().next());
+
+        if let Some(source) = source {
+            let found = search(vec![format!("source:{source}")])
+                .await?
+                .into_iter()
+                .filter(|post| {
+                    post.source
+                        .as_deref()
+                        .is_some_and(|s| s.split_whitespace().any(|s| same_source(s, source)))
+                })
+                .map(|post| (post, Confidence::High))
+                .collect::<Vec<_>>();
+
+            if !found.is_empty() {
+                return Ok(found);
+            }
+        }
+
+        if origin.width == 0 || origin.height == 0 || matcher.tags == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Longer tags tend to be names of characters, series and artists rather than what
+        // half of the booru is tagged with.
+        let mut tags = origin.tags.split_whitespace().collect::<Vec<_>>();
+        tags.sort_by_key(|tag| Reverse(tag.len()));
+        tags.truncate(matcher.tags);
+        if tags.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(search(tags.into_iter().map(String::from).collect())
+            .await?
+            .into_iter()
+            .filter(|post| (post.width, post.height) == (origin.width, origin.height))
+            .filter(|post| overlap(&origin.tags, &post.tags) >= matcher.overlap)
+            .map(|post| (post, Confidence::Low))
+            .collect())
+    }
+}
+
+/// Share of tags `a` and `b` have in common, as the size of their intersection over the size of
+/// their union. Tags are space separated, like in [`BooruPost::tags`], and compared without case.
+pub fn overlap(a: &str, b: &str) -> f32 {
+    let a = a
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<HashSet<_>>();
+    let b = b
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<HashSet<_>>();
+
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+/// Whether two sources point at the same place, ignoring the scheme, a leading `www.`, a
+/// trailing slash and the case of the host.
+pub fn same_source(a: &str, b: &str) -> bool {
+    fn normalize(source: &str) -> String {
+        let source = source.trim();
+        let source = source
+            .split_once("://")
+            .map_or(source, |(_, rest)| rest)
+            .trim_end_matches('/');
+        let (host, path) = source.split_once('/').unwrap_or((source, ""));
+        let host = host.to_lowercase();
+
+        format!("{}/{path}", host.strip_prefix("www.").unwrap_or(&host))
+    }
+
+    normalize(a) == normalize(b)
+}
@@ -1,15 +1,23 @@
+pub mod booru;
 pub mod client;
+pub mod federated;
+pub mod matching;
+pub mod tags;
 
 use serde::{Deserialize, Serialize};
-use strum::Display;
+use strum::{Display, EnumIter};
 
-#[derive(Display, Debug, Clone)]
+use crate::shared::pages::WithId;
+
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, Default, EnumIter, Serialize, Deserialize)]
 #[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum Rating {
     Explicit,
     Questionable,
     Safe,
     Sensitive,
+    #[default]
     General,
 }
 
@@ -25,7 +33,14 @@ pub struct BooruPost {
     pub tags: String,
     pub image: Option<String>,
     pub source: Option<String>,
-    pub rating: Rating,
+    /// `None` when the booru didn't send a rating or sent one the crate doesn't know.
+    pub rating: Option<Rating>,
+}
+
+impl WithId for BooruPost {
+    fn id(&self) -> u32 {
+        self.id
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,235 @@
+//! Tag names shared across boorus.
+//!
+//! Boorus agree on tags like `1girl` but often not on characters, series and artists, which each
+//! site names its own way. A [`TagMapping`] picks one canonical name for every tag, and
+//! [`GenericClient`] uses it to translate the tags of queries into the names of the booru they are
+//! sent to, and the tags of the posts it gets back into canonical names.
+//!
+//! [`TagTable`] is the mapping the crate provides, filled from alias data such as Danbooru's
+//! `/tag_aliases.json` and from tables of the application.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Debug,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::{client::ClientQueryBuilder, Tag};
+
+use super::{client::GenericClient, AutoCompleteItem, BooruPost};
+
+/// Translates tags between their canonical names and the names boorus give them. Boorus are
+/// identified by [`BooruOption::name`](super::client::BooruOption::name).
+pub trait TagMapping: Debug + Send + Sync {
+    /// Name `booru` gives to the canonical `tag`.
+    fn translate(&self, booru: &str, tag: &str) -> String;
+
+    /// Canonical name of `tag`, as named by `booru`.
+    fn normalize(&self, booru: &str, tag: &str) -> String;
+}
+
+/// A [`TagMapping`] held in tables.
+///
+/// ```
+/// use rusty_booru::generic::tags::{TagMapping, TagTable};
+///
+/// let mut table = TagTable::new();
+/// table
+///     .alias("chino_kafuu", "kafuu_chino")
+///     .name("gelbooru", "gochuumon_wa_usagi_desu_ka?", "is_the_order_a_rabbit?");
+///
+/// assert_eq!("kafuu_chino", table.normalize("gelbooru", "chino_kafuu"));
+/// assert_eq!(
+///     "is_the_order_a_rabbit?",
+///     table.translate("gelbooru", "gochuumon_wa_usagi_desu_ka?")
+/// );
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(from = "Tables")]
+pub struct TagTable {
+    /// Canonical name of other names a tag goes by, on any booru.
+    pub aliases: HashMap<String, String>,
+    /// Canonical name of the tags one booru names differently, by booru. Only changed through
+    /// [`TagTable::name`] so it stays in step with `names`.
+    boorus: HashMap<String, HashMap<String, String>>,
+    /// `boorus` the other way around: the names of each canonical tag, by booru.
+    #[serde(skip)]
+    names: HashMap<String, HashMap<String, BTreeSet<String>>>,
+}
+
+/// What a [`TagTable`] is read from.
+#[derive(Deserialize)]
+struct Tables {
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+    #[serde(default)]
+    boorus: HashMap<String, HashMap<String, String>>,
+}
+
+impl From<Tables> for TagTable {
+    fn from(tables: Tables) -> Self {
+        let mut table = TagTable {
+            aliases: tables.aliases,
+            ..Default::default()
+        };
+
+        for (booru, names) in tables.boorus {
+            for (name, canonical) in names {
+                table.name(booru.clone(), canonical, name);
+            }
+        }
+        table
+    }
+}
+
+/// An entry of Danbooru's `/tag_aliases.json`.
+#[derive(Deserialize)]
+struct DanbooruAlias {
+    antecedent_name: String,
+    consequent_name: String,
+    #[serde(default)]
+    status: Option<String>,
+}
+
+impl TagTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `alias` another name of `canonical`, on every booru.
+    pub fn alias(&mut self, alias: impl Into<String>, canonical: impl Into<String>) -> &mut Self {
+        self.aliases.insert(alias.into(), canonical.into());
+        self
+    }
+
+    /// Makes `name` the name `booru` gives to `canonical`.
+    pub fn name(
+        &mut self,
+        booru: impl Into<String>,
+        canonical: impl Into<String>,
+        name: impl Into<String>,
+    ) -> &mut Self {
+        let (booru, canonical, name) = (booru.into(), canonical.into(), name.into());
+
+        let previous = self
+            .boorus
+            .entry(booru.clone())
+            .or_default()
+            .insert(name.clone(), canonical.clone());
+
+        let names = self.names.entry(booru).or_default();
+        if let Some(previous) = previous {
+            if let Some(others) = names.get_mut(&previous) {
+                others.remove(&name);
+                if others.is_empty() {
+                    names.remove(&previous);
+                }
+            }
+        }
+        names.entry(canonical).or_default().insert(name);
+        self
+    }
+
+    /// Canonical name of the tags `booru` names differently, by the booru's name.
+    pub fn names_of(&self, booru: &str) -> Option<&HashMap<String, String>> {
+        self.boorus.get(booru)
+    }
+
+    /// Adds the entries of `other`, which win over these.
+    pub fn extend(&mut self, other: TagTable) -> &mut Self {
+        self.aliases.extend(other.aliases);
+        for (booru, names) in other.boorus {
+            for (name, canonical) in names {
+                self.name(booru.clone(), canonical, name);
+            }
+        }
+        self
+    }
+
+    /// Adds the active aliases of a page of Danbooru's `/tag_aliases.json`, whose consequents
+    /// become the canonical names.
+    pub fn danbooru_aliases(&mut self, json: &str) -> Result<&mut Self, serde_json::Error> {
+        let aliases: Vec<DanbooruAlias> = serde_json::from_str(json)?;
+
+        for alias in aliases {
+            if alias
+                .status
+                .as_deref()
+                .is_none_or(|status| status == "active")
+            {
+                self.alias(alias.antecedent_name, alias.consequent_name);
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl TagMapping for TagTable {
+    fn translate(&self, booru: &str, tag: &str) -> String {
+        let canonical = self.aliases.get(tag).map_or(tag, String::as_str);
+
+        // Several names of a booru may lead to the same tag, the first in alphabetical order is
+        // picked so queries don't change from one run to the next.
+        self.names
+            .get(booru)
+            .and_then(|names| names.get(canonical)?.first())
+            .map_or(canonical, String::as_str)
+            .to_string()
+    }
+
+    fn normalize(&self, booru: &str, tag: &str) -> String {
+        let tag = self
+            .boorus
+            .get(booru)
+            .and_then(|names| names.get(tag))
+            .map_or(tag, String::as_str);
+
+        self.aliases
+            .get(tag)
+            .map_or(tag, String::as_str)
+            .to_string()
+    }
+}
+
+impl ClientQueryBuilder<GenericClient> {
+    /// This query with its tags named as `booru` names them.
+    pub(crate) fn translate(&self, mapping: &dyn TagMapping, booru: &str) -> Self {
+        let mut query = self.clone();
+
+        for tag in query.tags.0.iter_mut() {
+            match tag {
+                Tag::Plain(name) | Tag::Blacklist(name) => *name = mapping.translate(booru, name),
+                Tag::Rating(_) | Tag::Sort(_) => {}
+            }
+        }
+        query
+    }
+}
+
+impl BooruPost {
+    /// Renames the tags of this post, found on `booru`, to their canonical names.
+    pub(crate) fn normalize(&mut self, mapping: &dyn TagMapping, booru: &str) {
+        self.tags = self
+            .tags
+            .split_whitespace()
+            .map(|tag| mapping.normalize(booru, tag))
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+}
+
+impl AutoCompleteItem {
+    /// Renames the completed tag, found on `booru`, to its canonical name, in the label as well.
+    /// Labels holding more than the tag, like a post count, only get the tag replaced.
+    pub(crate) fn normalize(&mut self, mapping: &dyn TagMapping, booru: &str) {
+        let value = mapping.normalize(booru, &self.value);
+
+        self.label = if !self.value.is_empty() && self.label.contains(&self.value) {
+            self.label.replace(&self.value, &value)
+        } else {
+            mapping.normalize(booru, &self.label)
+        };
+        self.value = value;
+    }
+}
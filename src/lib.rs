@@ -23,8 +23,23 @@
 //! }
 //! ```
 
+#[cfg(not(any(
+    feature = "danbooru",
+    feature = "gelbooru",
+    feature = "safebooru",
+    feature = "sites"
+)))]
+compile_error!("enable at least one of the `danbooru`, `gelbooru`, `safebooru` and `sites` features");
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "danbooru")]
 pub mod danbooru;
+#[cfg(feature = "gelbooru")]
 pub mod gelbooru;
+#[cfg(feature = "safebooru")]
 pub mod safebooru;
 pub mod shared;
+#[cfg(feature = "sites")]
+pub mod site;
 pub mod generic;
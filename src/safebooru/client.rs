@@ -8,6 +8,7 @@ use crate::{
             ClientBuilder, ClientInformation, ClientQueryDispatcher, ClientTypes,
             ImplementedWithCommonQuery, QueryDispatcher, QueryLike, QueryMode, WithCommonQuery,
         },
+        transport::{Endpoint, HttpRequest},
     },
 };
 
@@ -17,6 +18,7 @@ use super::*;
 pub struct SafebooruClient(pub ClientBuilder<Self>);
 
 impl ClientInformation for SafebooruClient {
+    const NAME: &'static str = "safebooru";
     const URL: &'static str = "https://safebooru.org";
     const SORT: &'static str = "sort:";
 }
@@ -36,39 +38,59 @@ impl QueryDispatcher<SafebooruClient> for ClientQueryDispatcher<SafebooruClient>
     async fn get_autocomplete<In: Into<String> + Send>(
         &self,
         input: In,
-    ) -> Result<Vec<crate::generic::AutoCompleteItem>, reqwest::Error> {
-        self.builder
-            .client
-            .get(format!("{}/autocomplete.php", self.builder.url))
-            .query(&[("q", input.into())])
-            .send()
-            .await?
-            .json::<Vec<AutoCompleteItem>>()
-            .await
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        self.traced("get_autocomplete", async move {
+            self.builder
+                .send(
+                    HttpRequest::get(format!("{}/autocomplete.php", self.builder.url))
+                        .query([("q", input.into())])
+                        .endpoint(Endpoint::Autocomplete),
+                )
+                .await?
+                .error_for_status()?
+                .json_or_default()
+        })
+        .await
     }
 
     async fn get_by_id(&self, id: u32) -> Result<Option<SafebooruPost>, shared::Error> {
-        self.builder
-            .client
-            .get(&format!("{}/index.php", &self.builder.url))
-            .query(&Self::get_query(QueryMode::Single(id)))
-            .send()
-            .await?
-            .json::<Vec<SafebooruPost>>()
-            .await
-            .map(|r| r.into_iter().next())
-            .map_err(Into::into)
+        self.traced("get_by_id", async move {
+            self.builder
+                .send(
+                    HttpRequest::get(format!("{}/index.php", &self.builder.url))
+                        .query(Self::get_query(QueryMode::Single(id)))
+                        .endpoint(Endpoint::Post),
+                )
+                .await?
+                .error_for_status()?
+                .json_or_default::<Vec<SafebooruPost>>()
+                .map(|r| r.into_iter().next().map(|post| self.resolve(post)))
+        })
+        .await
     }
 
     async fn get(&self) -> Result<Vec<SafebooruPost>, shared::Error> {
-        self.builder
-            .client
-            .get(format!("{}/index.php", &self.builder.url))
-            .query(&Self::get_query(QueryMode::Multiple(&self.query)))
-            .send()
-            .await?
-            .json::<Vec<SafebooruPost>>()
-            .await
-            .map_err(Into::into)
+        self.traced("get", async move {
+            self.builder
+                .send(
+                    HttpRequest::get(format!("{}/index.php", &self.builder.url))
+                        .query(Self::get_query(QueryMode::Multiple(&self.query)))
+                        .endpoint(Endpoint::Posts),
+                )
+                .await?
+                .error_for_status()?
+                .json_or_default::<Vec<SafebooruPost>>()
+                .map(|r| r.into_iter().map(|post| self.resolve(post)).collect())
+        })
+        .await
+    }
+}
+
+impl ClientQueryDispatcher<SafebooruClient> {
+    /// Resolves the post's links against the url this dispatcher was built with,
+    /// so mirrors don't end up pointing back at safebooru.org.
+    fn resolve(&self, mut post: SafebooruPost) -> SafebooruPost {
+        post.resolve_urls(&self.builder.url);
+        post
     }
 }
@@ -3,34 +3,79 @@ pub mod client;
 use derive_more::From;
 
 use serde::Deserialize;
+use serde_json::{Map, Value};
 use strum::Display;
 
 use crate::{
     generic::{BooruPost, Rating},
-    shared::client::ClientInformation,
+    shared::{
+        client::ClientInformation,
+        interner::{InternTags, Interned, InternedTags, TagInterner},
+        lenient,
+        pages::WithId,
+    },
 };
 
 use self::client::SafebooruClient;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct SafebooruPost {
+    #[serde(deserialize_with = "lenient::required_number")]
     pub id: u32,
+    #[serde(default, deserialize_with = "lenient::option_number")]
     pub score: Option<u32>,
     /// This can be `null` for really recent posts
-    pub height: u32,
+    #[serde(default, deserialize_with = "lenient::option_number")]
+    pub height: Option<u32>,
+    #[serde(default, deserialize_with = "lenient::number")]
     pub width: u32,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub hash: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub tags: String,
+    #[serde(default, deserialize_with = "lenient::string")]
     pub image: String,
 
     /// The directory where the safebooru image file is stored at.
+    #[serde(default, deserialize_with = "lenient::string")]
     pub directory: String,
 
     /// This is basically equivalent to `updated_at` in a Danbooru post. Except
     /// that it's provided as a UNIX timestamp. Safebooru provides no `created_at`
     /// field.
+    #[serde(default, deserialize_with = "lenient::number")]
     pub change: u32,
-    pub rating: SafebooruRating,
+    #[serde(default, deserialize_with = "lenient::known")]
+    pub rating: Option<SafebooruRating>,
+
+    /// Link to the full sized image. Filled in from the client's base url by
+    /// [`SafebooruPost::resolve_urls`] when the API leaves it out.
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub file_url: Option<String>,
+
+    /// Link to the post's thumbnail, resolved the same way as `file_url`.
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub preview_url: Option<String>,
+
+    /// Any field that isn't modeled above, kept as sent by the API.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl SafebooruPost {
+    /// Fills in any links the API didn't provide, relative to `base_url`. Links
+    /// that were already sent by the API are kept as is.
+    pub fn resolve_urls(&mut self, base_url: &str) {
+        self.file_url.get_or_insert_with(|| {
+            format!("{}/images/{}/{}", base_url, self.directory, self.image)
+        });
+        self.preview_url.get_or_insert_with(|| {
+            format!(
+                "{}/thumbnails/{}/thumbnail_{}.jpg",
+                base_url, self.directory, self.hash
+            )
+        });
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Display, From)]
@@ -69,25 +114,39 @@ impl From<SafebooruRating> for Rating {
 }
 
 impl From<SafebooruPost> for BooruPost {
-    fn from(post: SafebooruPost) -> Self {
+    fn from(mut post: SafebooruPost) -> Self {
+        // Posts that came through a dispatcher are already resolved against its
+        // url, this only matters for posts that were built by hand.
+        post.resolve_urls(SafebooruClient::URL);
+
         Self {
             id: post.id,
             created_at: None,
             score: post.score.unwrap_or_default().into(),
             width: post.width,
-            height: post.height,
-            md5: None,
-            file_url: format!(
-                "{}/images/{}/{}",
-                SafebooruClient::URL,
-                post.directory,
-                post.image
-            )
-            .into(),
+            height: post.height.unwrap_or_default(),
+            // Safebooru names the file's md5 `hash`.
+            md5: Some(post.hash).filter(|hash| !hash.is_empty()),
+            file_url: post.file_url,
             tags: post.tags,
-            image: post.image.into(),
+            image: Some(post.image).filter(|image| !image.is_empty()),
             source: None,
-            rating: post.rating.into(),
+            rating: post.rating.map(Into::into),
         }
     }
 }
+
+impl WithId for SafebooruPost {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl InternTags for SafebooruPost {
+    type Tags = InternedTags;
+
+    fn intern_tags(mut self, interner: &TagInterner) -> Interned<Self> {
+        let tags = interner.intern_all(&std::mem::take(&mut self.tags));
+        Interned { post: self, tags }
+    }
+}
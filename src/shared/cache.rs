@@ -0,0 +1,350 @@
+//! Opt-in caching of API responses.
+//!
+//! Responses are keyed by the normalized request, so two searches for the same tags in a different
+//! order share their entry. How long an entry stays fresh depends on the [`Endpoint`] it came from,
+//! a post looked up by id barely changes while a search can get new results any minute.
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    num::NonZeroUsize,
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+use itertools::Itertools;
+use lru::LruCache;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use super::transport::{is_secret, Endpoint, HttpRequest, HttpResponse};
+
+/// A response as kept by a [`CacheStore`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub stored_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+impl CachedResponse {
+    pub fn new(response: &HttpResponse, ttl: Duration) -> Self {
+        let now = SystemTime::now();
+
+        Self {
+            status: response.status.as_u16(),
+            headers: response
+                .headers
+                .iter()
+                .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+                .collect(),
+            body: response.body.clone(),
+            stored_at: now,
+            expires_at: now + ttl,
+        }
+    }
+
+    /// Marks the response as fresh for another `ttl`, after the API confirmed it didn't change.
+    pub fn refresh(&mut self, ttl: Duration) {
+        self.expires_at = SystemTime::now() + ttl;
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.expires_at > SystemTime::now()
+    }
+
+    pub fn to_response(&self) -> HttpResponse {
+        let mut headers = HeaderMap::new();
+
+        for (k, v) in &self.headers {
+            if let (Ok(k), Ok(v)) = (HeaderName::try_from(k), HeaderValue::try_from(v)) {
+                headers.append(k, v);
+            }
+        }
+
+        HttpResponse {
+            status: StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK),
+            headers,
+            body: self.body.clone(),
+        }
+    }
+}
+
+/// Somewhere to keep cached responses.
+///
+/// Stores are free to keep expired entries around, they are only served again once revalidated.
+/// Failures are not reported, a broken cache simply behaves like an empty one.
+pub trait CacheStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+    fn put(&self, key: &str, response: CachedResponse);
+    fn remove(&self, key: &str);
+    fn clear(&self);
+}
+
+/// In-memory store keeping the `capacity` most recently used responses.
+pub struct MemoryCache(Mutex<LruCache<String, CachedResponse>>);
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self(Mutex::new(LruCache::new(capacity)))
+    }
+}
+
+impl CacheStore for MemoryCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        self.0.lock().unwrap().put(key.to_string(), response);
+    }
+
+    fn remove(&self, key: &str) {
+        self.0.lock().unwrap().pop(key);
+    }
+
+    fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Store keeping one file per response inside a directory, so the cache survives restarts.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+/// What precedes the body in a cache file.
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    /// Second hash of the key, telling apart keys whose file names collide without writing the
+    /// key itself to disk.
+    check: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    stored_at: SystemTime,
+    expires_at: SystemTime,
+}
+
+impl DiskCache {
+    /// Uses `dir` to store the responses, creating it if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.cache", fnv(FNV_OFFSET, key)))
+    }
+
+    fn check(key: &str) -> String {
+        format!("{:016x}", fnv(FNV_OFFSET.rotate_left(32), key))
+    }
+
+    fn read(&self, key: &str) -> io::Result<CachedResponse> {
+        let mut file = BufReader::new(fs::File::open(self.path(key))?);
+
+        let mut header = String::new();
+        file.read_line(&mut header)?;
+        let entry: DiskEntry = serde_json::from_str(&header)?;
+
+        // Another key hashing to the same file.
+        if entry.check != Self::check(key) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let mut body = Vec::new();
+        file.read_to_end(&mut body)?;
+
+        Ok(CachedResponse {
+            status: entry.status,
+            headers: entry.headers,
+            body,
+            stored_at: entry.stored_at,
+            expires_at: entry.expires_at,
+        })
+    }
+
+    fn write(&self, key: &str, response: CachedResponse) -> io::Result<()> {
+        let entry = DiskEntry {
+            check: Self::check(key),
+            status: response.status,
+            headers: response.headers,
+            stored_at: response.stored_at,
+            expires_at: response.expires_at,
+        };
+
+        // Written next to the destination first, so readers never see half a file. Every write
+        // has a file of its own, so concurrent writers of the same key can't mix their bytes.
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+
+        let path = self.path(key);
+        let temp = path.with_extension(format!(
+            "{}.{}.tmp",
+            process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut file = fs::File::create(&temp)?;
+        serde_json::to_writer(&mut file, &entry)?;
+        file.write_all(b"\n")?;
+        file.write_all(&response.body)?;
+        drop(file);
+
+        fs::rename(temp, path)
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a, the std hasher isn't guaranteed to be stable between releases and file names have to
+/// stay the same across runs.
+fn fnv(offset: u64, key: &str) -> u64 {
+    key.bytes().fold(offset, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl CacheStore for DiskCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.read(key).ok()
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        let _ = self.write(key, response);
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+
+    fn clear(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            if entry.path().extension().is_some_and(|ext| ext == "cache") {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// How long responses stay fresh, per [`Endpoint`]. A zero duration disables caching for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTtl {
+    pub posts: Duration,
+    pub post: Duration,
+    pub autocomplete: Duration,
+    pub download: Duration,
+    pub other: Duration,
+}
+
+impl Default for CacheTtl {
+    fn default() -> Self {
+        Self {
+            posts: Duration::from_secs(60),
+            post: Duration::from_secs(60 * 60 * 24),
+            autocomplete: Duration::from_secs(60 * 60),
+            // Files are large and never change, they're better off in a cache of their own.
+            download: Duration::ZERO,
+            other: Duration::from_secs(60),
+        }
+    }
+}
+
+impl CacheTtl {
+    pub fn for_endpoint(&self, endpoint: Endpoint) -> Duration {
+        match endpoint {
+            Endpoint::Posts => self.posts,
+            Endpoint::Post => self.post,
+            Endpoint::Autocomplete => self.autocomplete,
+            Endpoint::Download => self.download,
+            Endpoint::Other => self.other,
+        }
+    }
+}
+
+/// Cache settings of a [`ClientBuilder`](super::client::ClientBuilder).
+#[derive(Clone)]
+pub struct Cache {
+    pub store: Arc<dyn CacheStore>,
+    pub ttl: CacheTtl,
+    /// Skip cached responses and always hit the API. Fresh responses are still stored.
+    pub bypass: bool,
+}
+
+impl std::fmt::Debug for Cache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cache")
+            .field("ttl", &self.ttl)
+            .field("bypass", &self.bypass)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cache {
+    pub fn new(store: impl CacheStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            ttl: CacheTtl::default(),
+            bypass: false,
+        }
+    }
+
+    /// Keep up to `capacity` responses in memory.
+    pub fn memory(capacity: usize) -> Self {
+        Self::new(MemoryCache::new(capacity))
+    }
+
+    /// Keep the responses as files inside `dir`.
+    pub fn disk(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        Ok(Self::new(DiskCache::new(dir)?))
+    }
+
+    pub fn ttl(mut self, ttl: CacheTtl) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn bypass(mut self, bypass: bool) -> Self {
+        self.bypass = bypass;
+        self
+    }
+
+    /// Key identifying `request`. Query pairs are sorted, and so are the tags of searches since
+    /// their order doesn't change the results. Credentials such as `api_key` are replaced by a
+    /// hash, so they are never written to a store but accounts still don't share entries.
+    pub fn key(request: &HttpRequest) -> String {
+        let query = request
+            .query
+            .iter()
+            .map(|(k, v)| match k.as_str() {
+                "tags" => (k.clone(), v.split_whitespace().sorted().join(" ")),
+                _ if is_secret(k) => (k.clone(), format!("{:016x}", fnv(FNV_OFFSET, v))),
+                _ => (k.clone(), v.clone()),
+            })
+            .sorted()
+            .collect_vec();
+
+        let url = reqwest::Url::parse_with_params(&request.url, &query)
+            .map(String::from)
+            .unwrap_or_else(|_| request.url.clone());
+
+        format!("{} {}", request.method, url)
+    }
+}
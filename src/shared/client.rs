@@ -1,34 +1,484 @@
 use std::{
     fmt::{Debug, Display},
     marker::PhantomData,
+    num::NonZeroU32,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use crate::generic::{AutoCompleteItem, BooruPost, Rating};
 
-use super::{Sort, Tag, Tags};
+use super::{
+    cache::{Cache, CachedResponse},
+    conditional::{Validator, Validators},
+    metrics::Metrics,
+    middleware::Middleware,
+    rate_limit::{RateLimit, RateLimiter},
+    response::{retry_after, RawResponse},
+    retry::RetryPolicy,
+    runtime::{self, default_runtime, Runtime},
+    trace,
+    transport::{
+        Endpoint, HttpOptions, HttpRequest, HttpResponse, HttpTransport, ReqwestTransport,
+    },
+    Sort, Tag, Tags,
+};
 use itertools::Itertools;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Method, Proxy, StatusCode,
+};
+
+/// Reqwest transport honoring `options`. Building a client only fails when the TLS backend can't
+/// be initialized, which is what `reqwest::Client::new` panics on as well.
+fn default_transport(options: &HttpOptions, runtime: &Arc<dyn Runtime>) -> ReqwestTransport {
+    ReqwestTransport {
+        runtime: runtime.clone(),
+        ..ReqwestTransport::from_options(options).expect("the HTTP client to be built")
+    }
+}
 
-#[derive(Debug)]
 pub struct ClientBuilder<T: ClientTypes> {
-    pub client: reqwest::Client,
+    pub transport: Arc<dyn HttpTransport>,
     pub url: String,
-
+    pub http: HttpOptions,
+    pub runtime: Arc<dyn Runtime>,
+    pub middleware: Vec<Arc<dyn Middleware>>,
+    pub metrics: Option<Arc<dyn Metrics>>,
+    pub rate_limiter: RateLimiter,
+    /// Limit applied to every host this builder sends requests to, `None` disables it.
+    pub rate_limit: Option<RateLimit>,
+    pub retry: RetryPolicy,
+    pub cache: Option<Cache>,
+    /// Validators of previous responses, `None` when conditional requests are disabled.
+    pub validators: Option<Validators>,
+
+    /// Report `304 Not Modified` as [`super::Error::NotModified`] even when there is a cached
+    /// copy to answer with, set by [`ClientQueryDispatcher::get_if_changed`].
+    report_unchanged: bool,
+    /// Whether [`ClientBuilder::transport`] replaced the default transport, which then isn't
+    /// rebuilt when the connection settings change.
+    custom_transport: bool,
     _marker: PhantomData<T>,
 }
 
 impl<T: ClientTypes> Clone for ClientBuilder<T> {
     fn clone(&self) -> Self {
         Self {
-            client: self.client.clone(),
+            transport: self.transport.clone(),
             url: self.url.clone(),
+            http: self.http.clone(),
+            runtime: self.runtime.clone(),
+            middleware: self.middleware.clone(),
+            metrics: self.metrics.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            rate_limit: self.rate_limit,
+            retry: self.retry.clone(),
+            cache: self.cache.clone(),
+            validators: self.validators.clone(),
+            report_unchanged: self.report_unchanged,
+            custom_transport: self.custom_transport,
             _marker: self._marker,
         }
     }
 }
 
+impl<T: ClientTypes> Debug for ClientBuilder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientBuilder")
+            .field("url", &self.url)
+            .field("http", &self.http)
+            .field("middleware", &self.middleware.len())
+            .field("rate_limit", &self.rate_limit)
+            .field("retry", &self.retry)
+            .field("cache", &self.cache)
+            .field("revalidate", &self.validators.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: ClientTypes + ClientInformation> ClientBuilder<T> {
+    /// Replace the transport used to send the requests, [`ReqwestTransport`] by default.
+    pub fn transport(&mut self, transport: impl HttpTransport + 'static) -> &mut Self {
+        self.transport = Arc::new(transport);
+        self.custom_transport = true;
+        self
+    }
+
+    /// Sleep with `runtime` for backoffs, rate limits and timeouts, see [`Runtime`].
+    pub fn runtime(&mut self, runtime: impl Runtime + 'static) -> &mut Self {
+        self.runtime = Arc::new(runtime);
+        self.rebuild_transport()
+    }
+
+    /// Replace all the connection settings at once.
+    pub fn http(&mut self, options: HttpOptions) -> &mut Self {
+        self.http = options;
+        self.rebuild_transport()
+    }
+
+    /// Identify the requests with `agent` rather than the crate's own user agent.
+    pub fn user_agent(&mut self, agent: HeaderValue) -> &mut Self {
+        self.http.user_agent = agent;
+        self
+    }
+
+    /// Send `value` as the `name` header with every request.
+    pub fn header(&mut self, name: HeaderName, value: HeaderValue) -> &mut Self {
+        self.http.headers.insert(name, value);
+        self
+    }
+
+    /// Add `key=value` to the query of every request, for things like API keys.
+    pub fn query_param(&mut self, key: impl ToString, value: impl ToString) -> &mut Self {
+        self.http.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Send the requests through an HTTP or SOCKS proxy.
+    pub fn proxy(&mut self, proxy: Proxy) -> &mut Self {
+        self.http.proxy = Some(proxy);
+        self.rebuild_transport()
+    }
+
+    pub fn connect_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.http.connect_timeout = Some(timeout);
+        self.rebuild_transport()
+    }
+
+    /// Give up on a response when its next chunk takes longer than `timeout` to arrive.
+    pub fn read_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.http.read_timeout = Some(timeout);
+        self.rebuild_transport()
+    }
+
+    /// Give up on an attempt that takes longer than `timeout` overall. Retried attempts each get
+    /// the full duration.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.http.timeout = Some(timeout);
+        self
+    }
+
+    pub fn no_timeouts(&mut self) -> &mut Self {
+        self.http.connect_timeout = None;
+        self.http.read_timeout = None;
+        self.http.timeout = None;
+        self.rebuild_transport()
+    }
+
+    fn rebuild_transport(&mut self) -> &mut Self {
+        if !self.custom_transport {
+            self.transport = Arc::new(default_transport(&self.http, &self.runtime));
+        }
+        self
+    }
+
+    /// Add `middleware` at the end of the chain, see [`Middleware`] for when it runs.
+    pub fn middleware(&mut self, middleware: impl Middleware + 'static) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Report what happens to the requests into `metrics`.
+    pub fn metrics(&mut self, metrics: impl Metrics + 'static) -> &mut Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Throttle requests to `limit` per host. Other builders sharing the same [`RateLimiter`]
+    /// keep their own limit but take from the same buckets.
+    pub fn rate_limit(&mut self, limit: RateLimit) -> &mut Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Send requests as fast as they come, responses asking to slow down are ignored too.
+    pub fn no_rate_limit(&mut self) -> &mut Self {
+        self.rate_limit = None;
+        self
+    }
+
+    /// Use `limiter` instead of [`RateLimiter::global`], to coordinate with a different set of
+    /// clients.
+    pub fn rate_limiter(&mut self, limiter: RateLimiter) -> &mut Self {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    /// Retry failed requests according to `policy`. Requests are only sent once by default.
+    pub fn retry(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Cache successful responses, see [`Cache`] for the available stores.
+    pub fn cache(&mut self, cache: Cache) -> &mut Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn no_cache(&mut self) -> &mut Self {
+        self.cache = None;
+        self
+    }
+
+    /// Send conditional requests using the `ETag`/`Last-Modified` of previous responses. Clones
+    /// of this builder share what they remember.
+    ///
+    /// Without a [`cache`](Self::cache), only [`ClientQueryDispatcher::get_if_changed`] sends
+    /// conditional requests, since there is no copy to answer the other calls with.
+    pub fn revalidate(&mut self) -> &mut Self {
+        self.validators.get_or_insert_with(Validators::default);
+        self
+    }
+
+    /// Downloads a file, usually one of the posts' `file_url`. Goes through the same rate limits
+    /// and retries as the API calls do.
+    pub async fn download(&self, url: &str) -> Result<Vec<u8>, super::Error> {
+        let result = self
+            .send(HttpRequest::get(url).endpoint(Endpoint::Download))
+            .await
+            .and_then(RawResponse::error_for_status)
+            .map(|response| response.body);
+
+        self.report_error("download", &result);
+        result
+    }
+
+    pub(crate) fn report_error<R>(&self, operation: &str, result: &Result<R, super::Error>) {
+        if let (Some(metrics), Err(e)) = (&self.metrics, result) {
+            metrics.error(T::NAME, operation, e.kind());
+        }
+    }
+
+    /// Sends the request through the transport. The response is handed back whatever its status
+    /// is, checking it is up to the caller.
+    pub(crate) async fn send(&self, mut request: HttpRequest) -> Result<RawResponse, super::Error> {
+        self.http.apply(&mut request);
+
+        let span = trace::Span::request(T::NAME, &request);
+        let response = span
+            .instrument(self.send_through_middleware(request, &span))
+            .await;
+        span.finish(&response, |response| {
+            span.status(response.status);
+            None
+        });
+
+        response
+    }
+
+    async fn send_through_middleware(
+        &self,
+        mut request: HttpRequest,
+        span: &trace::Span,
+    ) -> Result<RawResponse, super::Error> {
+        let mut ran = 0;
+        let mut response = None;
+
+        for middleware in &self.middleware {
+            ran += 1;
+            response = middleware.before(&mut request)?;
+            if response.is_some() {
+                break;
+            }
+        }
+
+        let mut response = match response {
+            Some(response) => response,
+            None => self.send_stateful(request.clone(), span).await?,
+        };
+
+        for middleware in self.middleware[..ran].iter().rev() {
+            middleware.after(&request, &mut response)?;
+        }
+
+        Ok(RawResponse::new(request.redacted_url(), response))
+    }
+
+    async fn send_stateful(
+        &self,
+        request: HttpRequest,
+        span: &trace::Span,
+    ) -> Result<HttpResponse, super::Error> {
+        let stateful = self.cache.is_some() || self.validators.is_some();
+
+        if stateful && request.method == Method::GET {
+            self.send_conditional(request, span).await
+        } else {
+            self.send_with_retries(request, span).await
+        }
+    }
+
+    /// Answers from the cache when possible, otherwise sends the request, as a conditional one
+    /// if we've seen its response before.
+    async fn send_conditional(
+        &self,
+        mut request: HttpRequest,
+        span: &trace::Span,
+    ) -> Result<HttpResponse, super::Error> {
+        let key = Cache::key(&request);
+        let cache = self
+            .cache
+            .as_ref()
+            .map(|cache| (cache, cache.ttl.for_endpoint(request.endpoint)))
+            .filter(|(_, ttl)| !ttl.is_zero());
+
+        let stored = cache.and_then(|(cache, _)| cache.store.get(&key));
+
+        if let Some((cache, _)) = cache {
+            let hit = stored
+                .as_ref()
+                .filter(|stored| stored.is_fresh() && !cache.bypass && !self.report_unchanged);
+
+            if let Some(metrics) = &self.metrics {
+                metrics.cache(T::NAME, request.endpoint, hit.is_some());
+            }
+            if let Some(hit) = hit {
+                return Ok(hit.to_response());
+            }
+        }
+
+        // A `304` is only useful with a copy to answer with, or to a caller asking whether
+        // anything changed. Plain calls without a stored copy ask for the full response.
+        let validators = self
+            .validators
+            .as_ref()
+            .filter(|_| self.report_unchanged || stored.is_some());
+
+        if let Some(validators) = validators {
+            let validator = validators
+                .get(&key)
+                .or_else(|| stored.as_ref().and_then(Validator::from_cached));
+
+            if let Some(validator) = validator {
+                validator.apply(&mut request.headers);
+            }
+        }
+
+        let response = self.send_with_retries(request, span).await?;
+
+        if response.status == StatusCode::NOT_MODIFIED {
+            return match (cache, stored) {
+                (Some((cache, ttl)), Some(mut stored)) if !self.report_unchanged => {
+                    stored.refresh(ttl);
+                    cache.store.put(&key, stored.clone());
+                    Ok(stored.to_response())
+                }
+                _ => Err(super::Error::NotModified),
+            };
+        }
+
+        if response.status.is_success() {
+            if let Some(validators) = &self.validators {
+                validators.remember(&key, &response.headers);
+            }
+            if let Some((cache, ttl)) = cache {
+                cache.store.put(&key, CachedResponse::new(&response, ttl));
+            }
+        }
+
+        Ok(response)
+    }
+
+    async fn send_with_retries(
+        &self,
+        request: HttpRequest,
+        span: &trace::Span,
+    ) -> Result<HttpResponse, super::Error> {
+        let host = reqwest::Url::parse(&request.url)
+            .ok()
+            .and_then(|url| url.host_str().map(ToString::to_string))
+            .unwrap_or_default();
+
+        let mut attempt = 1;
+
+        loop {
+            let result = self.send_once(request.clone(), &host).await;
+            span.attempt(attempt);
+
+            let delay = match &result {
+                Ok(response)
+                    if self.retry.should_retry_status(
+                        &request.method,
+                        attempt,
+                        response.status,
+                    ) =>
+                {
+                    // The server knows better than our backoff when it's telling us to wait, but
+                    // waits longer than the policy allows are left to the caller, who gets them in
+                    // `Error::RateLimited` rather than a call parked for hours.
+                    match retry_after(&response.headers) {
+                        Some(wait) if wait > self.retry.max_backoff => return result,
+                        Some(wait) => wait,
+                        None => self.retry.delay(attempt),
+                    }
+                }
+                Err(e) if self.retry.should_retry_error(&request.method, attempt, e) => {
+                    self.retry.delay(attempt)
+                }
+                _ => return result,
+            };
+
+            self.runtime.sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_once(
+        &self,
+        request: HttpRequest,
+        host: &str,
+    ) -> Result<HttpResponse, super::Error> {
+        if let Some(limit) = self.rate_limit {
+            let waited = self
+                .rate_limiter
+                .acquire_with(host, limit, &*self.runtime)
+                .await;
+
+            if let Some(metrics) = self.metrics.as_ref().filter(|_| !waited.is_zero()) {
+                metrics.rate_limited(T::NAME, waited);
+            }
+        }
+
+        let endpoint = request.endpoint;
+        let started = Instant::now();
+
+        let response = match self.http.timeout {
+            Some(timeout) => {
+                runtime::timeout(&*self.runtime, timeout, self.transport.send(request))
+                    .await
+                    .ok_or(super::Error::Timeout(timeout))??
+            }
+            None => self.transport.send(request).await?,
+        };
+
+        if let Some(metrics) = &self.metrics {
+            metrics.request(
+                T::NAME,
+                endpoint,
+                response.status,
+                started.elapsed(),
+                response.body.len(),
+            );
+        }
+
+        if self.rate_limit.is_some() {
+            self.rate_limiter
+                .observe(host, response.status, &response.headers);
+        }
+
+        Ok(response)
+    }
+}
+
 pub trait ClientInformation {
+    /// Name of the backend, as it appears in logs.
+    const NAME: &'static str;
     const URL: &'static str;
     const SORT: &'static str;
+    /// Default request rate for the booru, see [`ClientBuilder::rate_limit`].
+    const RATE_LIMIT: RateLimit = RateLimit::per_second(NonZeroU32::new(2).unwrap());
 }
 
 pub trait ClientTypes {
@@ -61,7 +511,7 @@ pub trait QueryDispatcher<T: ClientTypes> {
     fn get_autocomplete<In: Into<String> + Send>(
         &self,
         input: In,
-    ) -> impl std::future::Future<Output = Result<Vec<AutoCompleteItem>, reqwest::Error>> + Send;
+    ) -> impl std::future::Future<Output = Result<Vec<AutoCompleteItem>, crate::shared::Error>> + Send;
 
     /// Pack the [`ClientBuilder`] and sent the request to the API to retrieve the posts
     fn get_by_id(
@@ -104,10 +554,17 @@ impl<T: WithCommonQuery + ClientTypes + ClientInformation> ImplementedWithCommon
         let extension = match query_type {
             QueryLike::Gelbooru => match query_mode {
                 QueryMode::Single(id) => vec![("id", id.to_string())],
-                QueryMode::Multiple(query) => vec![
-                    ("limit", query.limit.to_string()),
-                    ("tags", query.tags.unpack()),
-                ],
+                QueryMode::Multiple(query) => {
+                    let mut pairs = vec![
+                        ("limit", query.limit.to_string()),
+                        ("tags", query.tags.unpack()),
+                    ];
+                    // Gelbooru counts its pages from 0.
+                    if let Some(page) = query.page {
+                        pairs.push(("pid", page.saturating_sub(1).to_string()));
+                    }
+                    pairs
+                }
             },
         }
         .into_iter()
@@ -129,6 +586,8 @@ impl<T: WithCommonQuery + ClientTypes + ClientInformation> ImplementedWithCommon
 pub struct ClientQueryBuilder<T: ClientTypes> {
     pub tags: Tags<T>,
     pub limit: u32,
+    /// Page of the results to retrieve, starting from 1. `None` is the first page.
+    pub page: Option<u32>,
 }
 
 impl<T: ClientTypes + Clone> Default for ClientQueryBuilder<T> {
@@ -142,6 +601,7 @@ impl<T: ClientTypes + Clone> ClientQueryBuilder<T> {
         Self {
             tags: Tags(Vec::new()),
             limit: 100,
+            page: None,
         }
     }
 
@@ -175,6 +635,12 @@ impl<T: ClientTypes + Clone> ClientQueryBuilder<T> {
         self.limit = limit;
         self
     }
+
+    /// Retrieve the `page`th page of results, starting from 1. Pages hold `limit` posts each.
+    pub fn page(&mut self, page: u32) -> &mut Self {
+        self.page = Some(page);
+        self
+    }
 }
 
 impl<T: ClientTypes + Clone> ClientBuilder<T> {
@@ -208,12 +674,76 @@ pub struct ClientQueryDispatcher<T: ClientTypes> {
     pub query: ClientQueryBuilder<T>,
 }
 
+impl<T: ClientTypes + Clone> ClientQueryDispatcher<T>
+where
+    Self: QueryDispatcher<T>,
+{
+    /// Same as [`QueryDispatcher::get`], but answers `None` when the API reports that nothing
+    /// changed since the previous call. Needs [`ClientBuilder::revalidate`] to be enabled.
+    pub async fn get_if_changed(&self) -> Result<Option<Vec<T::Post>>, super::Error> {
+        let mut dispatcher = self.clone();
+        dispatcher.builder.report_unchanged = true;
+
+        match dispatcher.get().await {
+            Ok(posts) => Ok(Some(posts)),
+            Err(super::Error::NotModified) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<T: ClientTypes + ClientInformation> ClientQueryDispatcher<T> {
+    /// Runs a [`QueryDispatcher`] call inside its own span.
+    pub(crate) async fn traced<R: trace::Count>(
+        &self,
+        operation: &str,
+        call: impl std::future::Future<Output = Result<R, super::Error>>,
+    ) -> Result<R, super::Error> {
+        let span = trace::Span::query(
+            T::NAME,
+            operation,
+            || self.query.tags.unpack(),
+            self.query.limit,
+            self.query.page,
+        );
+        let result = span.instrument(call).await;
+        span.finish(&result, |r| Some(r.count()));
+        self.builder.report_error(operation, &result);
+
+        result
+    }
+}
+
+impl<T: ClientTypes> ClientQueryDispatcher<T> {
+    /// Ignore cached responses for the requests sent by this dispatcher. What the API answers
+    /// still replaces the cached entry.
+    pub fn bypass_cache(&mut self) -> &mut Self {
+        if let Some(cache) = &mut self.builder.cache {
+            cache.bypass = true;
+        }
+        self
+    }
+}
+
 impl<T: ClientInformation + ClientTypes> ClientBuilder<T> {
     pub fn new() -> Self {
+        let http = HttpOptions::global();
+        let runtime = default_runtime();
+
         Self {
-            client: reqwest::Client::new(),
+            transport: Arc::new(default_transport(&http, &runtime)),
+            http,
+            runtime,
+            middleware: Vec::new(),
+            metrics: None,
             url: T::URL.to_string(),
-
+            rate_limiter: RateLimiter::global(),
+            rate_limit: Some(T::RATE_LIMIT),
+            retry: RetryPolicy::none(),
+            cache: None,
+            validators: None,
+            report_unchanged: false,
+            custom_transport: false,
             _marker: PhantomData,
         }
     }
@@ -0,0 +1,88 @@
+//! Conditional requests, so polling the same search costs close to nothing while nothing changes.
+//!
+//! The `ETag` and `Last-Modified` headers of successful responses are remembered per request and
+//! sent back as `If-None-Match`/`If-Modified-Since` the next time there is a cached copy to
+//! answer with, which is used when the API answers with `304 Not Modified`.
+//! [`get_if_changed`](super::client::ClientQueryDispatcher::get_if_changed) always sends them and
+//! reports a `304` as nothing new.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use lru::LruCache;
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+
+use super::cache::CachedResponse;
+
+/// How many requests [`Validators`] remember by default.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// What identifies a version of a response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validator {
+    pub etag: Option<HeaderValue>,
+    pub last_modified: Option<HeaderValue>,
+}
+
+impl Validator {
+    /// Validator of a response, if it sent any.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let validator = Self {
+            etag: headers.get(ETAG).cloned(),
+            last_modified: headers.get(LAST_MODIFIED).cloned(),
+        };
+
+        (validator.etag.is_some() || validator.last_modified.is_some()).then_some(validator)
+    }
+
+    pub fn from_cached(response: &CachedResponse) -> Option<Self> {
+        Self::from_headers(&response.to_response().headers)
+    }
+
+    /// Turns the request into a conditional one.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Some(etag) = &self.etag {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+    }
+}
+
+/// The validators of the most recent requests, keyed like the [`Cache`](super::cache::Cache).
+/// Cloning it is cheap and clones share the same entries.
+#[derive(Debug, Clone)]
+pub struct Validators(Arc<Mutex<LruCache<String, Validator>>>);
+
+impl Default for Validators {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Validators {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self(Arc::new(Mutex::new(LruCache::new(capacity))))
+    }
+
+    pub fn get(&self, key: &str) -> Option<Validator> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    /// Remembers the validator sent along with a response, if any.
+    pub fn remember(&self, key: &str, headers: &HeaderMap) {
+        if let Some(validator) = Validator::from_headers(headers) {
+            self.0.lock().unwrap().put(key.to_string(), validator);
+        }
+    }
+
+    pub fn forget(&self, key: &str) {
+        self.0.lock().unwrap().pop(key);
+    }
+}
@@ -0,0 +1,160 @@
+//! Compact tag storage for when a lot of posts have to be kept around.
+//!
+//! Every distinct tag is stored once inside a [`TagInterner`] and posts only keep a list of
+//! [`TagId`]s pointing into it. The interner is cheap to clone and every clone refers to the same
+//! table, so it can be shared across tasks scanning different pages.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::generic::BooruPost;
+
+/// Identifier of a tag inside a [`TagInterner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TagId(u32);
+
+impl TagId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct Table {
+    ids: HashMap<Arc<str>, TagId>,
+    names: Vec<Arc<str>>,
+}
+
+/// Shared table of tag names.
+#[derive(Debug, Clone, Default)]
+pub struct TagInterner(Arc<RwLock<Table>>);
+
+impl TagInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the id of `tag`, adding it to the table if it's new.
+    pub fn intern(&self, tag: &str) -> TagId {
+        if let Some(id) = self.get(tag) {
+            return id;
+        }
+
+        let mut table = self.0.write().unwrap();
+
+        // Someone else may have added it while we were waiting for the lock.
+        if let Some(id) = table.ids.get(tag) {
+            return *id;
+        }
+
+        let id = TagId(table.names.len() as u32);
+        let name: Arc<str> = tag.into();
+
+        table.names.push(name.clone());
+        table.ids.insert(name, id);
+        id
+    }
+
+    /// Interns every tag of a space separated tag string, like the ones sent by the APIs.
+    pub fn intern_all(&self, tags: &str) -> InternedTags {
+        InternedTags(tags.split_whitespace().map(|tag| self.intern(tag)).collect())
+    }
+
+    /// Looks up the id of `tag` without adding it.
+    pub fn get(&self, tag: &str) -> Option<TagId> {
+        self.0.read().unwrap().ids.get(tag).copied()
+    }
+
+    /// Name of the tag behind `id`, `None` when it's out of this interner's range. Ids don't
+    /// record which interner they came from, so an id of another interner that happens to be in
+    /// range resolves to whatever tag this one has under it.
+    pub fn resolve(&self, id: TagId) -> Option<Arc<str>> {
+        self.0.read().unwrap().names.get(id.index()).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap().names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// List of tags stored as ids into a [`TagInterner`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct InternedTags(Box<[TagId]>);
+
+impl InternedTags {
+    pub fn ids(&self) -> &[TagId] {
+        &self.0
+    }
+
+    pub fn contains(&self, id: TagId) -> bool {
+        self.0.contains(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Resolves every tag back into its name, with the interner the tags were interned with. Ids
+    /// out of its range are skipped, see [`TagInterner::resolve`].
+    pub fn names(&self, interner: &TagInterner) -> Vec<Arc<str>> {
+        self.0.iter().filter_map(|id| interner.resolve(*id)).collect()
+    }
+
+    /// Rebuilds the space separated tag string the API originally sent.
+    pub fn to_tag_string(&self, interner: &TagInterner) -> String {
+        self.names(interner).join(" ")
+    }
+}
+
+impl FromIterator<TagId> for InternedTags {
+    fn from_iter<I: IntoIterator<Item = TagId>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// A post whose tags were moved into a [`TagInterner`].
+#[derive(Debug, Clone)]
+pub struct Interned<P: InternTags> {
+    /// The post itself, with its tag strings emptied.
+    pub post: P,
+    pub tags: P::Tags,
+}
+
+/// Posts that can trade their tag strings for interned ids.
+pub trait InternTags: Sized {
+    /// How the post keeps its tags once interned. Most boorus only have a flat list, but some of
+    /// them also split the tags by category.
+    type Tags: Into<InternedTags>;
+
+    /// Moves the post's tags into `interner`, freeing its own tag strings.
+    fn intern_tags(self, interner: &TagInterner) -> Interned<Self>;
+}
+
+impl<P: InternTags + Into<BooruPost>> Interned<P> {
+    /// Converts into the generic post while keeping the tags interned.
+    pub fn into_generic(self) -> Interned<BooruPost> {
+        Interned {
+            post: self.post.into(),
+            tags: self.tags.into(),
+        }
+    }
+}
+
+impl InternTags for BooruPost {
+    type Tags = InternedTags;
+
+    fn intern_tags(mut self, interner: &TagInterner) -> Interned<Self> {
+        let tags = interner.intern_all(&std::mem::take(&mut self.tags));
+        Interned { post: self, tags }
+    }
+}
@@ -0,0 +1,119 @@
+//! Forgiving deserializers for the API models.
+//!
+//! Booru APIs are not particularly strict about their own schemas, numbers come back as strings,
+//! fields turn `null` for recent posts and so on. These helpers are meant to be used with
+//! `#[serde(deserialize_with = "...")]` so a small change on the API side doesn't break the whole
+//! response.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de::DeserializeOwned, de::Error, Deserialize, Deserializer};
+use serde_json::Value;
+
+fn parse<T, E>(value: Value) -> Result<Option<T>, E>
+where
+    T: FromStr,
+    T::Err: Display,
+    E: Error,
+{
+    let text = match value {
+        Value::Null => return Ok(None),
+        Value::Bool(b) => u8::from(b).to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s,
+        other => return Err(E::custom(format!("expected a number, got {other}"))),
+    };
+
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    // Floats such as `12.0` are still accepted for integer fields.
+    text.parse::<T>()
+        .or_else(|e| match text.parse::<f64>() {
+            Ok(f) if f.fract() == 0.0 => format!("{f:.0}").parse::<T>(),
+            _ => Err(e),
+        })
+        .map(Some)
+        .map_err(E::custom)
+}
+
+/// Accepts a number, a stringly typed number or `null`, which falls back to the default.
+pub fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Default,
+    T::Err: Display,
+{
+    parse(Value::deserialize(deserializer)?).map(Option::unwrap_or_default)
+}
+
+/// Same as [`number`], for fields without a sensible default: `null` and empty strings are
+/// errors.
+pub fn required_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    parse(Value::deserialize(deserializer)?)?.ok_or_else(|| D::Error::custom("missing number"))
+}
+
+/// Same as [`number`], but keeps `null` and empty strings as [`None`].
+pub fn option_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    parse(Value::deserialize(deserializer)?)
+}
+
+/// Accepts a string, a number or `null`, which becomes an empty string.
+pub fn string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    option_string(deserializer).map(Option::unwrap_or_default)
+}
+
+/// Same as [`string`], but keeps `null` and empty strings as [`None`].
+pub fn option_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Null => None,
+        Value::String(s) if s.is_empty() => None,
+        Value::String(s) => Some(s),
+        other => Some(other.to_string()),
+    })
+}
+
+/// Accepts a boolean, `0`/`1` (also as strings), `"true"`/`"false"` or `null`.
+pub fn boolean<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(b) => b,
+        Value::String(s) => matches!(s.trim(), "1" | "true" | "True"),
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        _ => false,
+    })
+}
+
+/// Accepts a variant of `T`, keeping `null`, empty strings and values `T` doesn't know about as
+/// [`None`] rather than failing the whole post or guessing a default.
+pub fn known<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Null => None,
+        Value::String(s) if s.trim().is_empty() => None,
+        other => T::deserialize(other).ok(),
+    })
+}
@@ -0,0 +1,264 @@
+//! Counters describing how the clients use the boorus.
+//!
+//! Builders report into the [`Metrics`] they were given with [`ClientBuilder::metrics`]. Nothing
+//! is collected by default, and the `prometheus` feature adds [`PrometheusMetrics`], which keeps
+//! the counters in memory and renders them in the Prometheus text format.
+//!
+//! [`ClientBuilder::metrics`]: super::client::ClientBuilder::metrics
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use super::transport::Endpoint;
+
+/// Receives what happens to the requests. Every method does nothing by default.
+///
+/// `backend` is the [`ClientInformation::NAME`](super::client::ClientInformation::NAME) of the
+/// client reporting.
+pub trait Metrics: Send + Sync {
+    /// A request got a response, retried requests report every attempt.
+    fn request(
+        &self,
+        backend: &str,
+        endpoint: Endpoint,
+        status: StatusCode,
+        latency: Duration,
+        bytes: usize,
+    ) {
+        let _ = (backend, endpoint, status, latency, bytes);
+    }
+
+    /// A call failed. `operation` is the [`QueryDispatcher`](super::client::QueryDispatcher)
+    /// method or `download`, and `kind` comes from [`Error::kind`](super::Error::kind).
+    fn error(&self, backend: &str, operation: &str, kind: &str) {
+        let _ = (backend, operation, kind);
+    }
+
+    /// A cacheable request was either answered from the cache or had to hit the API.
+    fn cache(&self, backend: &str, endpoint: Endpoint, hit: bool) {
+        let _ = (backend, endpoint, hit);
+    }
+
+    /// A request had to wait `wait` for the rate limiter before being sent.
+    fn rate_limited(&self, backend: &str, wait: Duration) {
+        let _ = (backend, wait);
+    }
+}
+
+#[cfg(feature = "prometheus")]
+pub use self::prometheus::PrometheusMetrics;
+
+#[cfg(feature = "prometheus")]
+mod prometheus {
+    use std::{
+        collections::BTreeMap,
+        fmt::Write,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use reqwest::StatusCode;
+
+    use super::Metrics;
+    use crate::shared::transport::Endpoint;
+
+    /// Upper bounds of the latency histogram buckets, in seconds.
+    const BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+    type Labels = Vec<(&'static str, String)>;
+
+    #[derive(Default)]
+    struct Histogram {
+        buckets: [u64; BUCKETS.len()],
+        sum: f64,
+        count: u64,
+    }
+
+    impl Histogram {
+        fn observe(&mut self, value: f64) {
+            for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+                if value <= *bound {
+                    *bucket += 1;
+                }
+            }
+            self.sum += value;
+            self.count += 1;
+        }
+    }
+
+    #[derive(Default)]
+    struct Registry {
+        requests: BTreeMap<Labels, u64>,
+        latency: BTreeMap<Labels, Histogram>,
+        bytes: BTreeMap<Labels, u64>,
+        errors: BTreeMap<Labels, u64>,
+        cache: BTreeMap<Labels, u64>,
+        rate_limit_wait: BTreeMap<Labels, f64>,
+    }
+
+    /// [`Metrics`] kept in memory, ready to be scraped through [`PrometheusMetrics::render`].
+    /// Clones share the same counters, so one can be handed to every builder.
+    #[derive(Clone, Default)]
+    pub struct PrometheusMetrics(Arc<Mutex<Registry>>);
+
+    impl std::fmt::Debug for PrometheusMetrics {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("PrometheusMetrics").finish_non_exhaustive()
+        }
+    }
+
+    fn labels(pairs: &[(&'static str, &str)]) -> Labels {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    fn format_labels(labels: &Labels, extra: Option<(&str, String)>) -> String {
+        let pairs = labels
+            .iter()
+            .map(|(k, v)| (*k, v.clone()))
+            .chain(extra)
+            .map(|(k, v)| format!("{k}=\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect::<Vec<_>>();
+
+        format!("{{{}}}", pairs.join(","))
+    }
+
+    fn write_family<V: std::fmt::Display>(
+        out: &mut String,
+        name: &str,
+        kind: &str,
+        help: &str,
+        values: &BTreeMap<Labels, V>,
+    ) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for (labels, value) in values {
+            let _ = writeln!(out, "{name}{} {value}", format_labels(labels, None));
+        }
+    }
+
+    impl PrometheusMetrics {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// The counters in the Prometheus text exposition format.
+        pub fn render(&self) -> String {
+            let registry = self.0.lock().unwrap();
+            let mut out = String::new();
+
+            write_family(
+                &mut out,
+                "booru_requests_total",
+                "counter",
+                "Responses received, by status.",
+                &registry.requests,
+            );
+
+            let name = "booru_request_duration_seconds";
+            let _ = writeln!(out, "# HELP {name} Time taken by each attempt.");
+            let _ = writeln!(out, "# TYPE {name} histogram");
+            for (labels, histogram) in &registry.latency {
+                let bounds = BUCKETS.iter().map(ToString::to_string);
+                for (bound, count) in bounds.zip(histogram.buckets) {
+                    let labels = format_labels(labels, Some(("le", bound)));
+                    let _ = writeln!(out, "{name}_bucket{labels} {count}");
+                }
+                let inf = format_labels(labels, Some(("le", "+Inf".to_string())));
+                let labels = format_labels(labels, None);
+                let _ = writeln!(out, "{name}_bucket{inf} {}", histogram.count);
+                let _ = writeln!(out, "{name}_sum{labels} {}", histogram.sum);
+                let _ = writeln!(out, "{name}_count{labels} {}", histogram.count);
+            }
+
+            write_family(
+                &mut out,
+                "booru_response_bytes_total",
+                "counter",
+                "Bytes of response bodies received.",
+                &registry.bytes,
+            );
+            write_family(
+                &mut out,
+                "booru_errors_total",
+                "counter",
+                "Failed calls, by kind of error.",
+                &registry.errors,
+            );
+            write_family(
+                &mut out,
+                "booru_cache_requests_total",
+                "counter",
+                "Cacheable requests, by whether the cache could answer them.",
+                &registry.cache,
+            );
+            write_family(
+                &mut out,
+                "booru_rate_limit_wait_seconds_total",
+                "counter",
+                "Time spent waiting for the rate limiter.",
+                &registry.rate_limit_wait,
+            );
+
+            out
+        }
+    }
+
+    impl Metrics for PrometheusMetrics {
+        fn request(
+            &self,
+            backend: &str,
+            endpoint: Endpoint,
+            status: StatusCode,
+            latency: Duration,
+            bytes: usize,
+        ) {
+            let endpoint = endpoint.to_string();
+            let mut registry = self.0.lock().unwrap();
+
+            let key = labels(&[
+                ("backend", backend),
+                ("endpoint", &endpoint),
+                ("status", status.as_str()),
+            ]);
+            *registry.requests.entry(key).or_default() += 1;
+
+            let key = labels(&[("backend", backend), ("endpoint", &endpoint)]);
+            registry
+                .latency
+                .entry(key.clone())
+                .or_default()
+                .observe(latency.as_secs_f64());
+            *registry.bytes.entry(key).or_default() += bytes as u64;
+        }
+
+        fn error(&self, backend: &str, operation: &str, kind: &str) {
+            let key = labels(&[
+                ("backend", backend),
+                ("operation", operation),
+                ("kind", kind),
+            ]);
+            *self.0.lock().unwrap().errors.entry(key).or_default() += 1;
+        }
+
+        fn cache(&self, backend: &str, endpoint: Endpoint, hit: bool) {
+            let key = labels(&[
+                ("backend", backend),
+                ("endpoint", &endpoint.to_string()),
+                ("result", if hit { "hit" } else { "miss" }),
+            ]);
+            *self.0.lock().unwrap().cache.entry(key).or_default() += 1;
+        }
+
+        fn rate_limited(&self, backend: &str, wait: Duration) {
+            let key = labels(&[("backend", backend)]);
+            *self
+                .0
+                .lock()
+                .unwrap()
+                .rate_limit_wait
+                .entry(key)
+                .or_default() += wait.as_secs_f64();
+        }
+    }
+}
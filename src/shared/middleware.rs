@@ -0,0 +1,31 @@
+//! Hooks running around every request a [`ClientBuilder`](super::client::ClientBuilder) sends,
+//! whether it's a search, an autocompletion, a lookup or a download.
+//!
+//! Middlewares run in the order they were added before the request is sent, and in the reverse
+//! order once the response is back. They sit in front of everything else, so a rewritten url is
+//! what the cache and the rate limiter see, and a cached response still goes through
+//! [`Middleware::after`].
+
+use super::{
+    transport::{HttpRequest, HttpResponse},
+    Error,
+};
+
+pub trait Middleware: Send + Sync {
+    /// Called before the request is sent, with the connection settings already applied.
+    ///
+    /// Returning a response short-circuits the request: nothing is sent and the response is
+    /// handed to the `after` hooks of the middlewares that already ran, this one included.
+    /// Returning an error aborts the request.
+    fn before(&self, request: &mut HttpRequest) -> Result<Option<HttpResponse>, Error> {
+        let _ = request;
+        Ok(None)
+    }
+
+    /// Called with the response, whatever its status is. Requests that got no response at all
+    /// don't reach it.
+    fn after(&self, request: &HttpRequest, response: &mut HttpResponse) -> Result<(), Error> {
+        let _ = (request, response);
+        Ok(())
+    }
+}
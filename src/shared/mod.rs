@@ -1,24 +1,110 @@
+#[cfg(feature = "danbooru")]
 use crate::danbooru::client::DanbooruError;
 
 use self::client::{ClientInformation, ClientTypes};
 use derive_is_enum_variant::is_enum_variant;
 use itertools::Itertools;
-use std::fmt::Display;
-use strum::Display;
+use reqwest::StatusCode;
+use std::{fmt::Display, time::Duration};
+use strum::{Display, IntoStaticStr};
 
+pub mod cache;
 pub mod client;
+pub mod conditional;
+pub mod interner;
+pub mod lenient;
+pub mod metrics;
+pub mod middleware;
+pub mod pages;
+pub mod rate_limit;
+pub(crate) mod response;
+pub mod retry;
+pub mod runtime;
+pub(crate) mod trace;
+pub mod transport;
 
-#[derive(derive_more::From, Debug, thiserror::Error, Display)]
+#[derive(Debug, thiserror::Error, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum Error {
+    /// The request couldn't be sent or its body couldn't be read.
     #[error(transparent)]
-    Reqwest(reqwest::Error),
+    Reqwest(#[from] reqwest::Error),
 
+    /// Same as [`Error::Reqwest`], for transports that aren't backed by `reqwest`.
     #[error(transparent)]
-    Danbooru(DanbooruError),
+    Transport(Box<dyn std::error::Error + Send + Sync>),
 
+    #[cfg(feature = "danbooru")]
+    #[error(transparent)]
+    Danbooru(#[from] DanbooruError),
+
+    /// The API answered with `429 Too Many Requests`. `retry_after` is taken from the
+    /// `Retry-After` header, if it was sent.
+    #[error("rate limited by the API (retry after {retry_after:?})")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("the requested resource was not found")]
+    NotFound,
+
+    /// No booru was registered under the name of a
+    /// [`BooruOption::Custom`](crate::generic::client::BooruOption::Custom).
+    #[error("no booru registered as {0:?}")]
+    UnknownBooru(String),
+
+    /// The booru has no way of doing what was asked, e.g. autocompleting tags.
+    #[error("{0} is not supported by this booru")]
+    Unsupported(&'static str),
+
+    /// No response, or no part of it, came within the configured timeout.
+    #[error("the request timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// The API answered a conditional request with `304 Not Modified` and there was no cached
+    /// copy to answer with instead.
+    #[error("the resource didn't change since the last request")]
+    NotModified,
+
+    #[error("the request requires authentication")]
+    Unauthorized,
+
+    #[error("the request was forbidden")]
+    Forbidden,
+
+    #[error("the API failed with {status}")]
+    Server { status: StatusCode },
+
+    /// The API refused the query itself, holds whatever it had to say about it.
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+
+    /// Some APIs answer errors with an HTML page instead of JSON. `snippet` holds the start of the
+    /// body to help figuring out what went wrong.
+    #[error("unexpected content type {content_type:?}: {snippet}")]
+    UnexpectedContentType {
+        content_type: Option<String>,
+        snippet: String,
+    },
+
+    #[error("failed to decode the response from {url}: {source}")]
+    Decode {
+        url: String,
+        source: serde_json::Error,
+    },
+
+    #[error("the API answered with an unexpected {status}: {snippet}")]
+    UnexpectedStatus { status: StatusCode, snippet: String },
+
+    #[error("unexpected error")]
     Unexpected,
 }
 
+impl Error {
+    /// Short name of the variant, e.g. `not_found`, for labelling metrics.
+    pub fn kind(&self) -> &'static str {
+        self.into()
+    }
+}
+
 #[derive(Debug, Clone, Display)]
 #[strum(serialize_all = "lowercase")]
 pub enum Sort {
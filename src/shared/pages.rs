@@ -0,0 +1,81 @@
+//! Fetching several pages of a query at once.
+//!
+//! Pages are requested concurrently, but never more than a given number at a time, and every
+//! request still goes through the rate limiter of its builder.
+
+use std::collections::HashSet;
+
+use futures_util::{stream, StreamExt};
+
+use super::{
+    client::{ClientInformation, ClientQueryDispatcher, ClientTypes, QueryDispatcher},
+    Error,
+};
+
+/// Posts that can tell their id, so the same post showing up on two pages is only kept once.
+pub trait WithId {
+    fn id(&self) -> u32;
+}
+
+/// What [`ClientQueryDispatcher::get_pages`] retrieved.
+#[derive(Debug)]
+pub struct Pages<P> {
+    /// Posts of every page that succeeded, in page order, without duplicates.
+    pub posts: Vec<P>,
+    /// Pages that failed, with what went wrong.
+    pub failures: Vec<(u32, Error)>,
+}
+
+impl<P> Pages<P> {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl<T: ClientTypes + ClientInformation + Clone> ClientQueryDispatcher<T>
+where
+    Self: QueryDispatcher<T>,
+    T::Post: WithId,
+{
+    /// Retrieves `pages`, with up to `concurrency` requests in flight. A failing page doesn't
+    /// stop the others, it's reported in [`Pages::failures`] instead.
+    pub async fn get_pages(
+        &self,
+        pages: impl IntoIterator<Item = u32>,
+        concurrency: usize,
+    ) -> Pages<T::Post> {
+        let requests = pages.into_iter().map(|page| {
+            let mut dispatcher = self.clone();
+            dispatcher.query.page = Some(page);
+
+            async move { (page, dispatcher.get().await) }
+        });
+
+        let results = stream::iter(requests)
+            .buffered(concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut seen = HashSet::new();
+        let mut fetched = Pages {
+            posts: Vec::new(),
+            failures: Vec::new(),
+        };
+
+        for (page, result) in results {
+            match result {
+                Ok(posts) => fetched
+                    .posts
+                    .extend(posts.into_iter().filter(|post| seen.insert(post.id()))),
+                Err(e) => fetched.failures.push((page, e)),
+            }
+        }
+
+        fetched
+    }
+
+    /// Retrieves the first `count` pages, see [`ClientQueryDispatcher::get_pages`].
+    pub async fn get_first_pages(&self, count: u32, concurrency: usize) -> Pages<T::Post> {
+        self.get_pages(1..=count, concurrency).await
+    }
+}
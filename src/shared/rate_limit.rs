@@ -0,0 +1,200 @@
+//! Client side rate limiting, so many tasks sharing a client don't end up getting it banned.
+//!
+//! Requests are throttled per host with a token bucket. Builders share the process wide
+//! [`RateLimiter::global`] unless told otherwise, which means every clone of a
+//! [`ClientBuilder`](super::client::ClientBuilder), and the builders of every `GenericClient`,
+//! take their tokens from the same buckets.
+
+use std::{
+    collections::HashMap,
+    num::NonZeroU32,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+use reqwest::{header::HeaderMap, StatusCode};
+
+use super::{
+    response::retry_after,
+    runtime::{default_runtime, Runtime},
+};
+
+/// How long to back off after a `429` that didn't say for how long.
+const DEFAULT_PAUSE: Duration = Duration::from_secs(5);
+
+/// Allows `requests` requests every `per`, with bursts of up to `requests`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    requests: NonZeroU32,
+    per: Duration,
+}
+
+impl RateLimit {
+    /// # Panics
+    ///
+    /// If `per` is zero, which would allow infinitely many requests.
+    pub const fn new(requests: NonZeroU32, per: Duration) -> Self {
+        assert!(!per.is_zero(), "a rate limit can't be over a zero duration");
+        Self { requests, per }
+    }
+
+    pub const fn per_second(requests: NonZeroU32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    pub const fn requests(&self) -> NonZeroU32 {
+        self.requests
+    }
+
+    pub const fn per(&self) -> Duration {
+        self.per
+    }
+
+    fn burst(&self) -> f64 {
+        self.requests.get() as f64
+    }
+
+    fn refill_rate(&self) -> f64 {
+        self.burst() / self.per.as_secs_f64()
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            tokens: limit.burst(),
+            updated_at: Instant::now(),
+            paused_until: None,
+        }
+    }
+
+    /// Takes a token, or returns how long to wait before trying again.
+    fn take(&mut self, limit: RateLimit) -> Option<Duration> {
+        let now = Instant::now();
+
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Some(until - now);
+            }
+            self.paused_until = None;
+        }
+
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_rate()).min(limit.burst());
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (1.0 - self.tokens) / limit.refill_rate(),
+            ))
+        }
+    }
+
+    fn pause(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        self.paused_until = Some(
+            self.paused_until
+                .map_or(until, |current| current.max(until)),
+        );
+        self.tokens = 0.0;
+    }
+}
+
+/// Set of per host token buckets. Cloning it is cheap and clones share the same buckets.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter(Arc<Mutex<HashMap<String, Bucket>>>);
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The limiter used by every builder that wasn't given its own.
+    pub fn global() -> Self {
+        static GLOBAL: OnceLock<RateLimiter> = OnceLock::new();
+        GLOBAL.get_or_init(RateLimiter::new).clone()
+    }
+
+    /// Waits until a request to `host` is allowed, returning how long that took.
+    pub async fn acquire(&self, host: &str, limit: RateLimit) -> Duration {
+        self.acquire_with(host, limit, &*default_runtime()).await
+    }
+
+    /// Same as [`RateLimiter::acquire`], sleeping with `runtime`.
+    pub async fn acquire_with(
+        &self,
+        host: &str,
+        limit: RateLimit,
+        runtime: &dyn Runtime,
+    ) -> Duration {
+        let mut waited = Duration::ZERO;
+
+        loop {
+            let wait = self
+                .0
+                .lock()
+                .unwrap()
+                .entry(host.to_string())
+                .or_insert_with(|| Bucket::new(limit))
+                .take(limit);
+
+            match wait {
+                None => return waited,
+                Some(duration) => {
+                    runtime.sleep(duration).await;
+                    waited += duration;
+                }
+            }
+        }
+    }
+
+    /// Stops handing out tokens for `host` for a while.
+    pub fn pause(&self, host: &str, duration: Duration) {
+        if let Some(bucket) = self.0.lock().unwrap().get_mut(host) {
+            bucket.pause(duration);
+        }
+    }
+
+    /// Pauses `host` when a response says we've been going too fast, either with a `429` or with
+    /// rate limit headers reporting no requests left.
+    pub fn observe(&self, host: &str, status: StatusCode, headers: &HeaderMap) {
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            self.pause(host, retry_after(headers).unwrap_or(DEFAULT_PAUSE));
+        } else if let Some(duration) = exhausted_for(headers) {
+            self.pause(host, duration);
+        }
+    }
+}
+
+/// Reads the de facto `X-RateLimit-Remaining`/`X-RateLimit-Reset` headers, the reset being either
+/// a number of seconds or a UNIX timestamp.
+fn exhausted_for(headers: &HeaderMap) -> Option<Duration> {
+    let header =
+        |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.trim().parse().ok() };
+
+    if header("x-ratelimit-remaining")? > 0 {
+        return None;
+    }
+
+    let reset = header("x-ratelimit-reset");
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    match reset {
+        Some(reset) if reset < 1_000_000_000 => Some(Duration::from_secs(reset)),
+        Some(reset) => reset.checked_sub(now).map(Duration::from_secs),
+        None => Some(retry_after(headers).unwrap_or(DEFAULT_PAUSE)),
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::{Duration, SystemTime};
+
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER},
+    StatusCode,
+};
+use serde::de::DeserializeOwned;
+
+use super::{transport::HttpResponse, Error};
+
+/// How much of a body is kept around when reporting an error.
+const SNIPPET_LEN: usize = 256;
+
+/// A fully read response, so it can be inspected more than once when figuring out what went wrong.
+pub(crate) struct RawResponse {
+    pub url: String,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl RawResponse {
+    pub fn new(url: String, response: HttpResponse) -> Self {
+        Self {
+            url,
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        }
+    }
+
+    /// Start of the body as text.
+    pub fn snippet(&self) -> String {
+        let text = String::from_utf8_lossy(&self.body);
+        let text = text.trim();
+
+        match text.char_indices().nth(SNIPPET_LEN) {
+            Some((end, _)) => format!("{}...", &text[..end]),
+            None => text.to_string(),
+        }
+    }
+
+    pub fn content_type(&self) -> Option<String> {
+        self.headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(ToString::to_string)
+    }
+
+    /// The error matching this response's status, regardless of what the body says.
+    pub fn error(&self) -> Error {
+        match self.status {
+            StatusCode::TOO_MANY_REQUESTS => Error::RateLimited {
+                retry_after: retry_after(&self.headers),
+            },
+            StatusCode::NOT_FOUND => Error::NotFound,
+            StatusCode::UNAUTHORIZED => Error::Unauthorized,
+            StatusCode::FORBIDDEN => Error::Forbidden,
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                Error::InvalidQuery(self.snippet())
+            }
+            status if status.is_server_error() => Error::Server { status },
+            status => Error::UnexpectedStatus {
+                status,
+                snippet: self.snippet(),
+            },
+        }
+    }
+
+    pub fn error_for_status(self) -> Result<Self, Error> {
+        if self.status.is_success() {
+            Ok(self)
+        } else {
+            Err(self.error())
+        }
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        serde_json::from_slice(&self.body).map_err(|source| {
+            let content_type = self.content_type();
+            let is_json = content_type
+                .as_deref()
+                .is_none_or(|c| c.contains("json") || c.starts_with("text/javascript"));
+
+            // Responses are sometimes labeled as HTML even though they hold JSON, so the content
+            // type is only blamed once decoding has already failed.
+            if !is_json || self.body.trim_ascii_start().starts_with(b"<") {
+                Error::UnexpectedContentType {
+                    content_type,
+                    snippet: self.snippet(),
+                }
+            } else {
+                Error::Decode {
+                    url: self.url.clone(),
+                    source,
+                }
+            }
+        })
+    }
+
+    /// Same as [`RawResponse::json`], but an empty body is treated as `T::default()`.
+    /// Gelbooru-like APIs answer searches without results with nothing at all.
+    pub fn json_or_default<T: DeserializeOwned + Default>(&self) -> Result<T, Error> {
+        if self.body.trim_ascii().is_empty() {
+            return Ok(T::default());
+        }
+
+        self.json()
+    }
+}
+
+/// Parses `Retry-After`, which can either be a number of seconds or an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    httpdate::parse_http_date(value)
+        .ok()?
+        .duration_since(SystemTime::now())
+        .ok()
+}
@@ -0,0 +1,140 @@
+//! Retrying requests that failed for reasons that are likely to go away on their own, like a `502`
+//! from an overloaded booru or a timeout.
+
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode};
+
+use super::Error;
+
+/// How randomness is added to the backoff, so clients that failed together don't all come back
+/// at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// Always wait the full backoff.
+    None,
+    /// Wait anywhere between zero and the full backoff.
+    Full,
+    /// Wait at least half of the backoff.
+    Equal,
+}
+
+/// When and how often failed requests are sent again.
+///
+/// Only idempotent requests are ever retried, anything that could write on the booru's side is
+/// sent exactly once whatever the policy says.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, the first one included.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Factor the backoff grows by after each attempt.
+    pub multiplier: f64,
+    pub jitter: Jitter,
+    pub retry_statuses: Vec<StatusCode>,
+    /// Retry when the request couldn't be sent at all, e.g. timeouts and refused connections.
+    pub retry_transport_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: Jitter::Full,
+            retry_statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_transport_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry, this is what builders use unless told otherwise.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// # Panics
+    ///
+    /// If `multiplier` is negative or not finite.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        assert!(
+            multiplier.is_finite() && multiplier >= 0.0,
+            "the backoff multiplier must be a non-negative number, got {multiplier}"
+        );
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn retry_statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.retry_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    pub fn retry_transport_errors(mut self, retry: bool) -> Self {
+        self.retry_transport_errors = retry;
+        self
+    }
+
+    /// How long to wait before the attempt following `attempt`, starting from `1`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        // `max` and `min` also get rid of the NaN a multiplier set through the field can produce.
+        let backoff = backoff.max(0.0).min(self.max_backoff.as_secs_f64());
+
+        Duration::from_secs_f64(match self.jitter {
+            Jitter::None => backoff,
+            Jitter::Full => backoff * fastrand::f64(),
+            Jitter::Equal => backoff / 2.0 + backoff / 2.0 * fastrand::f64(),
+        })
+    }
+
+    fn allows(&self, method: &Method, attempt: u32) -> bool {
+        attempt < self.max_attempts && method.is_idempotent()
+    }
+
+    /// Whether a request that got `status` back on its `attempt`th try should be sent again.
+    pub fn should_retry_status(&self, method: &Method, attempt: u32, status: StatusCode) -> bool {
+        self.allows(method, attempt) && self.retry_statuses.contains(&status)
+    }
+
+    /// Whether a request that couldn't be sent on its `attempt`th try should be sent again.
+    pub fn should_retry_error(&self, method: &Method, attempt: u32, error: &Error) -> bool {
+        let transient = match error {
+            Error::Reqwest(e) => e.is_timeout() || e.is_connect(),
+            Error::Transport(_) | Error::Timeout(_) => true,
+            _ => false,
+        };
+
+        self.allows(method, attempt) && self.retry_transport_errors && transient
+    }
+}
@@ -0,0 +1,210 @@
+//! The little the clients need from an async runtime: waiting.
+//!
+//! Backoffs, rate limits and timeouts all sleep through the [`Runtime`] of their
+//! [`ClientBuilder`](super::client::ClientBuilder), so none of them ties the crate to an
+//! executor. [`TokioRuntime`] is used when the default `tokio` feature is enabled, and
+//! [`ThreadRuntime`] otherwise. Other executors only need a sleep function:
+//!
+//! ```ignore
+//! builder.runtime(|duration| async move {
+//!     smol::Timer::after(duration).await;
+//! });
+//! ```
+//!
+//! That isn't enough to leave tokio behind, the default
+//! [`ReqwestTransport`](super::transport::ReqwestTransport) connects through hyper, which needs
+//! a tokio reactor whatever the runtime is. Callers on other executors either enter a tokio
+//! runtime around their calls (`Handle::enter`), or give the builders an
+//! [`HttpTransport`](super::transport::HttpTransport) of their own as well:
+//!
+//! ```ignore
+//! builder
+//!     .runtime(ThreadRuntime)
+//!     .transport(|request: HttpRequest| async move { my_http_client.send(request).await });
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, OnceLock,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::transport::BoxFuture;
+
+pub trait Runtime: Send + Sync {
+    /// A future completing once `duration` has passed.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Sleeps with `tokio::time`, which must be driven by a tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio")]
+impl Runtime for TokioRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Sleeps on a timer thread shared by every sleep, so it works with any executor. The thread is
+/// started by the first sleep, and sleeps dropped before their time are forgotten.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRuntime;
+
+#[derive(Default)]
+struct Alarm {
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// Deadline of a sleep, along with a number telling apart sleeps ending at the same instant.
+type AlarmKey = (Instant, u64);
+
+/// Pending sleeps of every [`ThreadRuntime`], by deadline.
+#[derive(Default)]
+struct Timer {
+    alarms: Mutex<BTreeMap<AlarmKey, Arc<Mutex<Alarm>>>>,
+    changed: Condvar,
+}
+
+impl Timer {
+    fn get() -> &'static Timer {
+        static TIMER: OnceLock<Timer> = OnceLock::new();
+
+        TIMER.get_or_init(|| {
+            thread::Builder::new()
+                .name("rusty-booru-timer".to_string())
+                .spawn(|| Timer::get().run())
+                .expect("failed to spawn the timer thread");
+            Timer::default()
+        })
+    }
+
+    fn run(&self) {
+        let mut alarms = self.alarms.lock().unwrap();
+
+        loop {
+            let now = Instant::now();
+            let later = alarms.split_off(&(now, u64::MAX));
+            let due = std::mem::replace(&mut *alarms, later);
+
+            // Rung without holding the lock, so the woken tasks don't wait for it.
+            drop(alarms);
+            for alarm in due.into_values() {
+                let mut alarm = alarm.lock().unwrap();
+                alarm.done = true;
+                if let Some(waker) = alarm.waker.take() {
+                    waker.wake();
+                }
+            }
+            alarms = self.alarms.lock().unwrap();
+
+            alarms = match alarms.keys().next() {
+                Some(&(deadline, _)) => {
+                    let wait = deadline.saturating_duration_since(Instant::now());
+                    self.changed.wait_timeout(alarms, wait).unwrap().0
+                }
+                None => self.changed.wait(alarms).unwrap(),
+            };
+        }
+    }
+}
+
+/// A sleep of [`ThreadRuntime`], taken off the timer when dropped.
+struct ThreadSleep {
+    key: AlarmKey,
+    alarm: Arc<Mutex<Alarm>>,
+}
+
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut alarm = self.alarm.lock().unwrap();
+
+        if alarm.done {
+            return Poll::Ready(());
+        }
+        alarm.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for ThreadSleep {
+    fn drop(&mut self) {
+        Timer::get().alarms.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl Runtime for ThreadRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        static SLEEPS: AtomicU64 = AtomicU64::new(0);
+
+        let timer = Timer::get();
+        let sleep = ThreadSleep {
+            key: (
+                Instant::now() + duration,
+                SLEEPS.fetch_add(1, Ordering::Relaxed),
+            ),
+            alarm: Arc::default(),
+        };
+
+        let mut alarms = timer.alarms.lock().unwrap();
+        let sooner = alarms.keys().next().is_none_or(|&first| sleep.key < first);
+        alarms.insert(sleep.key, sleep.alarm.clone());
+        drop(alarms);
+
+        // The timer only needs to wake up early for a new first deadline.
+        if sooner {
+            timer.changed.notify_one();
+        }
+        Box::pin(sleep)
+    }
+}
+
+impl<F, Fut> Runtime for F
+where
+    F: Fn(Duration) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(self(duration))
+    }
+}
+
+/// The runtime builders use unless told otherwise.
+pub fn default_runtime() -> Arc<dyn Runtime> {
+    #[cfg(feature = "tokio")]
+    return Arc::new(TokioRuntime);
+
+    #[cfg(not(feature = "tokio"))]
+    Arc::new(ThreadRuntime)
+}
+
+/// Runs `future`, giving up with `None` once `duration` has passed.
+pub async fn timeout<F: Future>(
+    runtime: &dyn Runtime,
+    duration: Duration,
+    future: F,
+) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut sleep = runtime.sleep(duration);
+
+    std::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+
+        sleep.as_mut().poll(cx).map(|_| None)
+    })
+    .await
+}
@@ -0,0 +1,140 @@
+//! `tracing` instrumentation, compiled to nothing unless the `tracing` feature is enabled.
+//!
+//! Every [`QueryDispatcher`](super::client::QueryDispatcher) call gets a `booru_query` span, and
+//! every request sent through a [`ClientBuilder`](super::client::ClientBuilder) a nested
+//! `booru_request` one. Query values that look like credentials are redacted from the urls, errors
+//! are recorded by their [`kind`](super::Error::kind) and headers are never recorded.
+
+use std::future::Future;
+
+use super::{transport::HttpRequest, Error};
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, Instrument};
+
+/// How many results a call produced, recorded on its span.
+pub(crate) trait Count {
+    fn count(&self) -> usize;
+}
+
+impl<T> Count for Vec<T> {
+    fn count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> Count for Option<T> {
+    fn count(&self) -> usize {
+        self.is_some() as usize
+    }
+}
+
+/// A span being filled in as the call goes on.
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    started: Instant,
+}
+
+impl Span {
+    /// `tags` is only called when the feature is enabled.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub fn query(
+        backend: &str,
+        operation: &str,
+        tags: impl FnOnce() -> String,
+        limit: u32,
+        page: Option<u32>,
+    ) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "booru_query",
+                backend,
+                operation,
+                tags = tags(),
+                limit,
+                page,
+                results = Empty,
+                latency_ms = Empty,
+                error = Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            started: Instant::now(),
+        }
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub fn request(backend: &str, request: &HttpRequest) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "booru_request",
+                backend,
+                endpoint = %request.endpoint,
+                method = %request.method,
+                url = request.redacted_url(),
+                status = Empty,
+                attempts = Empty,
+                latency_ms = Empty,
+                error = Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            started: Instant::now(),
+        }
+    }
+
+    /// Runs `future` inside the span.
+    pub fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        #[cfg(feature = "tracing")]
+        return future.instrument(self.span.clone());
+
+        #[cfg(not(feature = "tracing"))]
+        future
+    }
+
+    /// Records that the request is being sent for the `attempt`th time.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub fn attempt(&self, attempt: u32) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("attempts", attempt);
+            if attempt > 1 {
+                tracing::debug!(parent: &self.span, attempt, "retrying request");
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub fn status(&self, status: reqwest::StatusCode) {
+        #[cfg(feature = "tracing")]
+        self.span.record("status", status.as_u16());
+    }
+
+    /// Records how the call ended, along with the number of results when it succeeded.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub fn finish<T>(&self, result: &Result<T, Error>, count: impl FnOnce(&T) -> Option<usize>) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span
+                .record("latency_ms", self.started.elapsed().as_millis() as u64);
+
+            match result {
+                Ok(value) => {
+                    if let Some(count) = count(value) {
+                        self.span.record("results", count);
+                    }
+                }
+                // Only the kind, errors may hold urls or bodies with credentials in them.
+                Err(e) => {
+                    self.span.record("error", e.kind());
+                    tracing::warn!(parent: &self.span, error = e.kind(), "booru call failed");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! The HTTP layer the clients send their requests through.
+//!
+//! Clients never talk to `reqwest` directly, they describe the request with an [`HttpRequest`] and
+//! hand it to the [`HttpTransport`] of their [`ClientBuilder`](super::client::ClientBuilder).
+//! [`ReqwestTransport`] is used by default, but anything implementing the trait can take its
+//! place, e.g. an in-process fake for tests or a proxy recording the traffic.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method, Proxy, StatusCode,
+};
+use strum::Display;
+
+use super::{
+    client::QueryVec,
+    runtime::{self, default_runtime, Runtime},
+    Error,
+};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Query keys whose values are credentials, kept out of errors, spans and cache keys.
+const SECRETS: &[&str] = &["key", "token", "pass", "secret", "auth", "login", "user_id"];
+
+/// Whether the value of the query pair `key` is a credential, e.g. `api_key` or `user_id`.
+pub(crate) fn is_secret(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRETS.iter().any(|secret| key.contains(secret))
+}
+
+/// What a request is for, so settings like cache lifetimes can differ between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Endpoint {
+    /// Searching for posts.
+    Posts,
+    /// A single post looked up by its id.
+    Post,
+    Autocomplete,
+    /// Files such as the posts' images.
+    Download,
+    #[default]
+    Other,
+}
+
+/// Description of a request, independent of the HTTP stack that will send it.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    /// Url without the query string.
+    pub url: String,
+    pub query: QueryVec,
+    pub headers: HeaderMap,
+    pub endpoint: Endpoint,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            query: Vec::new(),
+            headers: HeaderMap::new(),
+            endpoint: Endpoint::Other,
+        }
+    }
+
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    pub fn query<K: ToString, V: ToString>(
+        mut self,
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.query.extend(
+            pairs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        self
+    }
+
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// The full url, query string included.
+    pub fn full_url(&self) -> String {
+        reqwest::Url::parse_with_params(&self.url, &self.query)
+            .map(String::from)
+            .unwrap_or_else(|_| self.url.clone())
+    }
+
+    /// Same as [`HttpRequest::full_url`], with the values of credentials replaced by `REDACTED`.
+    pub fn redacted_url(&self) -> String {
+        let mut request = self.clone();
+
+        for (key, value) in &mut request.query {
+            if is_secret(key) {
+                *value = "REDACTED".to_string();
+            }
+        }
+        request.full_url()
+    }
+}
+
+/// Response as returned by a [`HttpTransport`], with its body already read.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: StatusCode, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+}
+
+/// Something able to send an [`HttpRequest`].
+///
+/// Unsuccessful statuses are not errors at this level, the clients need the response to figure
+/// out what went wrong. `Err` should only be returned when there is no response at all.
+pub trait HttpTransport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>>;
+}
+
+/// Connection settings of a [`ClientBuilder`](super::client::ClientBuilder).
+///
+/// The user agent, headers and query pairs are added to every request whatever the transport.
+/// The proxy and the connect/read timeouts configure the default [`ReqwestTransport`] and are
+/// up to custom transports to honor.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    pub user_agent: HeaderValue,
+    /// Sent along with every request, unless the request sets the header itself.
+    pub headers: HeaderMap,
+    /// Appended to the query of every request, e.g. `api_key` and `user_id`. Values of keys that
+    /// look like credentials are redacted from errors and spans, and hashed in cache keys.
+    pub query: QueryVec,
+    pub proxy: Option<Proxy>,
+    pub connect_timeout: Option<Duration>,
+    /// Longest wait for the next chunk of a response.
+    pub read_timeout: Option<Duration>,
+    /// Longest a single attempt may take, from connecting to reading the last byte.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            user_agent: HeaderValue::from_static(concat!(
+                "rusty-booru/",
+                env!("CARGO_PKG_VERSION"),
+                " (+https://github.com/o-dasher/rusty-booru)"
+            )),
+            headers: HeaderMap::new(),
+            query: Vec::new(),
+            proxy: None,
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            timeout: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+fn global_options() -> &'static RwLock<HttpOptions> {
+    static OPTIONS: OnceLock<RwLock<HttpOptions>> = OnceLock::new();
+    OPTIONS.get_or_init(Default::default)
+}
+
+impl HttpOptions {
+    /// Options new builders start with, [`HttpOptions::default`] unless changed with
+    /// [`HttpOptions::set_global`].
+    pub fn global() -> Self {
+        global_options().read().unwrap().clone()
+    }
+
+    /// Makes every builder created from now on start with these options, the clients
+    /// `GenericClient` creates included.
+    pub fn set_global(self) {
+        *global_options().write().unwrap() = self;
+    }
+
+    /// Adds the user agent, headers and query pairs to `request`.
+    pub fn apply(&self, request: &mut HttpRequest) {
+        if !request.headers.contains_key(reqwest::header::USER_AGENT) {
+            request
+                .headers
+                .insert(reqwest::header::USER_AGENT, self.user_agent.clone());
+        }
+
+        for (name, value) in &self.headers {
+            if !request.headers.contains_key(name) {
+                request.headers.insert(name, value.clone());
+            }
+        }
+
+        request.query.extend(self.query.iter().cloned());
+    }
+}
+
+/// Default transport, backed by a [`reqwest::Client`]. Its requests have to be sent from within
+/// a tokio runtime, see [`super::runtime`] for other executors.
+#[derive(Clone)]
+pub struct ReqwestTransport {
+    pub client: reqwest::Client,
+    /// Longest wait for the next chunk of a response body.
+    pub read_timeout: Option<Duration>,
+    /// What the read timeout is measured with.
+    pub runtime: Arc<dyn Runtime>,
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new(reqwest::Client::default())
+    }
+}
+
+impl std::fmt::Debug for ReqwestTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReqwestTransport")
+            .field("client", &self.client)
+            .field("read_timeout", &self.read_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            read_timeout: None,
+            runtime: default_runtime(),
+        }
+    }
+
+    /// Transport configured with the proxy and timeouts of `options`.
+    pub fn from_options(options: &HttpOptions) -> Result<Self, Error> {
+        let mut client = reqwest::Client::builder();
+
+        if let Some(proxy) = options.proxy.clone() {
+            client = client.proxy(proxy);
+        }
+        if let Some(timeout) = options.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+
+        Ok(Self {
+            client: client.build()?,
+            read_timeout: options.read_timeout,
+            runtime: default_runtime(),
+        })
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let mut response = self
+                .client
+                .request(request.method, request.url)
+                .query(&request.query)
+                .headers(request.headers)
+                .send()
+                .await
+                .map_err(reqwest::Error::without_url)?;
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            let mut body = Vec::new();
+
+            loop {
+                let chunk = match self.read_timeout {
+                    Some(timeout) => runtime::timeout(&*self.runtime, timeout, response.chunk())
+                        .await
+                        .ok_or(Error::Timeout(timeout))?,
+                    None => response.chunk().await,
+                };
+
+                match chunk.map_err(reqwest::Error::without_url)? {
+                    Some(chunk) => body.extend_from_slice(&chunk),
+                    None => break,
+                }
+            }
+
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+impl<F, Fut> HttpTransport for F
+where
+    F: Fn(HttpRequest) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<HttpResponse, Error>> + Send + 'static,
+{
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(self(request))
+    }
+}
@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use crate::{
+    generic::{booru::Booru, client::GenericClient, AutoCompleteItem, BooruPost, Rating},
+    shared::{
+        self,
+        client::{
+            ClientBuilder, ClientInformation, ClientQueryBuilder, ClientQueryDispatcher,
+            ClientTypes, ImplementedWithCommonQuery, QueryLike, QueryMode, WithCommonQuery,
+        },
+        response::RawResponse,
+        transport::{BoxFuture, Endpoint, HttpRequest},
+        Tag,
+    },
+};
+
+use super::*;
+
+/// Type of the [`ClientBuilder`] of every [`Site`]. As there is a single one for all of them,
+/// metrics and spans report every site as `site`.
+#[derive(Debug, Clone)]
+pub struct SiteClient;
+
+impl ClientInformation for SiteClient {
+    const NAME: &'static str = "site";
+    /// Sites have no url of their own, [`Site::new`] always sets the configured one.
+    const URL: &'static str = "";
+    const SORT: &'static str = "sort:";
+}
+
+impl ClientTypes for SiteClient {
+    type Post = SitePost;
+    type Rating = Rating;
+}
+
+impl WithCommonQuery for SiteClient {
+    fn common_query_type() -> QueryLike {
+        QueryLike::Gelbooru
+    }
+}
+
+/// Client of a site described by a [`SiteConfig`].
+#[derive(Debug, Clone)]
+pub struct Site {
+    pub config: Arc<SiteConfig>,
+    /// Sends the requests, change it to set up rate limits, caching and so on.
+    pub builder: ClientBuilder<SiteClient>,
+}
+
+impl Site {
+    pub fn new(config: SiteConfig) -> Self {
+        let mut builder = ClientBuilder::new();
+        builder.default_url(&config.url);
+
+        Self {
+            config: Arc::new(config),
+            builder,
+        }
+    }
+
+    /// `query` with its ratings named the way the site names them.
+    fn dispatcher(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+    ) -> ClientQueryDispatcher<SiteClient> {
+        let mut query = query.convert::<SiteClient>();
+
+        for tag in &mut query.tags.0 {
+            if let Tag::Rating(rating) = tag {
+                *tag = Tag::Plain(format!("rating:{}", self.config.rating_name(rating)));
+            }
+        }
+
+        self.builder.query_raw(&mut query)
+    }
+
+    fn api_query(&self, mode: QueryMode<SiteClient>) -> Vec<(String, String)> {
+        let mut query = ClientQueryDispatcher::get_query(mode);
+
+        if self.config.format == ResponseFormat::Xml {
+            query.retain(|(k, _)| k != "json");
+        }
+        query
+    }
+
+    fn posts(&self, response: RawResponse) -> Result<Vec<SitePost>, shared::Error> {
+        let mut posts: Vec<SitePost> = match self.config.format {
+            ResponseFormat::Json => response.json_or_default::<JsonPosts>()?.into(),
+            ResponseFormat::Xml if response.body.trim_ascii().is_empty() => Vec::new(),
+            ResponseFormat::Xml => parse_xml(&String::from_utf8_lossy(&response.body))
+                .ok_or_else(|| shared::Error::UnexpectedContentType {
+                    content_type: response.content_type(),
+                    snippet: response.snippet(),
+                })?
+                .into_iter()
+                .map(|fields| {
+                    serde_json::from_value(Value::Object(fields)).map_err(|source| {
+                        shared::Error::Decode {
+                            url: response.url.clone(),
+                            source,
+                        }
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
+        for post in &mut posts {
+            self.config.resolve(post);
+        }
+        Ok(posts)
+    }
+
+    pub async fn get(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+    ) -> Result<Vec<SitePost>, shared::Error> {
+        let dispatcher = self.dispatcher(query);
+
+        dispatcher
+            .traced("get", async {
+                let response = self
+                    .builder
+                    .send(
+                        HttpRequest::get(format!("{}/index.php", self.builder.url))
+                            .query(self.api_query(QueryMode::Multiple(&dispatcher.query)))
+                            .endpoint(Endpoint::Posts),
+                    )
+                    .await?
+                    .error_for_status()?;
+
+                self.posts(response)
+            })
+            .await
+    }
+
+    pub async fn get_by_id(&self, id: u32) -> Result<Option<SitePost>, shared::Error> {
+        self.builder
+            .dispatch()
+            .traced("get_by_id", async {
+                let response = self
+                    .builder
+                    .send(
+                        HttpRequest::get(format!("{}/index.php", self.builder.url))
+                            .query(self.api_query(QueryMode::Single(id)))
+                            .endpoint(Endpoint::Post),
+                    )
+                    .await?
+                    .error_for_status()?;
+
+                Ok(self.posts(response)?.into_iter().next())
+            })
+            .await
+    }
+
+    /// Fails with [`shared::Error::Unsupported`] for sites without
+    /// [`SiteConfig::autocomplete`].
+    pub async fn get_autocomplete(
+        &self,
+        query: &ClientQueryBuilder<GenericClient>,
+        input: impl Into<String>,
+    ) -> Result<Vec<AutoCompleteItem>, shared::Error> {
+        let input = input.into();
+
+        self.dispatcher(query)
+            .traced("get_autocomplete", async {
+                let Some(autocomplete) = &self.config.autocomplete else {
+                    return Err(shared::Error::Unsupported("autocomplete"));
+                };
+
+                self.builder
+                    .send(
+                        HttpRequest::get(format!("{}{}", self.builder.url, autocomplete.path))
+                            .query(&autocomplete.query)
+                            .query([(&autocomplete.param, &input)])
+                            .endpoint(Endpoint::Autocomplete),
+                    )
+                    .await?
+                    .error_for_status()?
+                    .json_or_default()
+            })
+            .await
+    }
+}
+
+impl Booru for Site {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn get<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+    ) -> BoxFuture<'a, Result<Vec<BooruPost>, shared::Error>> {
+        Box::pin(async move {
+            Site::get(self, query)
+                .await
+                .map(|v| v.into_iter().map(Into::into).collect())
+        })
+    }
+
+    fn get_by_id(&self, id: u32) -> BoxFuture<'_, Result<Option<BooruPost>, shared::Error>> {
+        Box::pin(async move { Site::get_by_id(self, id).await.map(|v| v.map(Into::into)) })
+    }
+
+    fn get_autocomplete<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+        input: String,
+    ) -> BoxFuture<'a, Result<Vec<AutoCompleteItem>, shared::Error>> {
+        Box::pin(Site::get_autocomplete(self, query, input))
+    }
+}
@@ -0,0 +1,339 @@
+//! Gelbooru-compatible sites defined at runtime.
+//!
+//! Plenty of boorus speak the Gelbooru 0.2 API (`index.php?page=dapi&s=post&q=index`) and only
+//! differ in their url, how they answer and a few details. Instead of a type per site, a
+//! [`SiteConfig`] describes one, usually read from a configuration file:
+//!
+//! ```
+//! # use rusty_booru::site::SiteConfig;
+//! let config: SiteConfig = serde_json::from_str(
+//!     r#"{
+//!         "name": "rule34",
+//!         "url": "https://api.rule34.xxx",
+//!         "format": "xml",
+//!         "file_url": "{url}/images/{directory}/{image}",
+//!         "ratings": { "general": "safe", "explicit": "explicit" },
+//!         "autocomplete": { "path": "/autocomplete.php", "param": "q" }
+//!     }"#,
+//! )?;
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+//!
+//! Any other format serde reads works too. [`client::Site`] turns the config into a client,
+//! which can be registered in a
+//! [`GenericClient`](crate::generic::client::GenericClient) with
+//! [`register_site`](crate::generic::client::GenericClient::register_site).
+
+pub mod client;
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use strum::IntoEnumIterator;
+
+use crate::{
+    generic::{BooruPost, Rating},
+    shared::{lenient, pages::WithId},
+};
+
+/// How a site answers its API calls.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    /// Asks for JSON with `json=1`. Both a bare list of posts and Gelbooru's `{"post": [...]}`
+    /// are understood.
+    #[default]
+    Json,
+    /// The original `<posts><post id="..." .../></posts>` format, with the fields as attributes.
+    Xml,
+}
+
+/// Where a site completes tag names, see [`SiteConfig::autocomplete`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AutocompleteConfig {
+    /// Path of the endpoint relative to the site's url, e.g. `/autocomplete.php`.
+    pub path: String,
+    /// Query parameter holding what was typed so far.
+    pub param: String,
+    /// Any other query parameter the endpoint needs.
+    #[serde(default)]
+    pub query: BTreeMap<String, String>,
+}
+
+/// Everything needed to talk to a Gelbooru-compatible site.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SiteConfig {
+    /// Name the site is registered under.
+    pub name: String,
+    /// Base url, without the trailing slash.
+    pub url: String,
+    #[serde(default)]
+    pub format: ResponseFormat,
+    /// Template for the file url of posts that come without one. `{url}` is replaced with the
+    /// site's url and `{id}`, `{md5}`, `{directory}` and `{image}` with the post's fields.
+    #[serde(default)]
+    pub file_url: Option<String>,
+    /// The site's name for each rating. Ratings left out keep their generic name.
+    #[serde(default)]
+    pub ratings: HashMap<Rating, String>,
+    /// `None` when the site has no autocompletion.
+    #[serde(default)]
+    pub autocomplete: Option<AutocompleteConfig>,
+}
+
+impl SiteConfig {
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            format: ResponseFormat::default(),
+            file_url: None,
+            ratings: HashMap::new(),
+            autocomplete: None,
+        }
+    }
+
+    /// What the site calls `rating`.
+    pub fn rating_name(&self, rating: &Rating) -> String {
+        self.ratings
+            .get(rating)
+            .cloned()
+            .unwrap_or_else(|| rating.to_string())
+    }
+
+    /// The generic rating the site's `name` stands for. Configured names take precedence over
+    /// the generic ones, names shared by several ratings resolve to the first of them in the
+    /// order of [`Rating`], and unknown ones to `None`.
+    pub fn rating(&self, name: &str) -> Option<Rating> {
+        let configured = Rating::iter().find(|rating| {
+            self.ratings
+                .get(rating)
+                .is_some_and(|n| n.eq_ignore_ascii_case(name))
+        });
+
+        configured
+            .or_else(|| Rating::iter().find(|rating| rating.to_string().eq_ignore_ascii_case(name)))
+    }
+
+    /// Fills in what the API left for the client to figure out.
+    pub fn resolve(&self, post: &mut SitePost) {
+        post.rating = self.rating(&post.raw_rating);
+
+        if let (None, Some(template)) = (&post.file_url, &self.file_url) {
+            let file_url = template
+                .replace("{url}", &self.url)
+                .replace("{id}", &post.id.to_string())
+                .replace("{md5}", post.md5().unwrap_or_default())
+                .replace("{directory}", post.directory.as_deref().unwrap_or_default())
+                .replace("{image}", post.image.as_deref().unwrap_or_default());
+            post.file_url = Some(file_url);
+        }
+    }
+}
+
+/// Post of a Gelbooru-compatible site, with the fields most of them agree on.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SitePost {
+    #[serde(deserialize_with = "lenient::required_number")]
+    pub id: u32,
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub created_at: Option<String>,
+    #[serde(default, deserialize_with = "lenient::number")]
+    pub score: i64,
+    #[serde(default, deserialize_with = "lenient::number")]
+    pub width: u32,
+    #[serde(default, deserialize_with = "lenient::number")]
+    pub height: u32,
+    /// Sent by Gelbooru itself.
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub md5: Option<String>,
+    /// Sent instead of `md5` by most of its clones.
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub hash: Option<String>,
+    /// Filled in from [`SiteConfig::file_url`] when the API leaves it out.
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub file_url: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub preview_url: Option<String>,
+    #[serde(default, deserialize_with = "lenient::string")]
+    pub tags: String,
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub image: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub directory: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string")]
+    pub source: Option<String>,
+    /// The rating as the site named it.
+    #[serde(default, rename = "rating", deserialize_with = "lenient::string")]
+    pub raw_rating: String,
+    /// `raw_rating` translated with [`SiteConfig::ratings`], `None` when the site sent none or one
+    /// it isn't configured for.
+    #[serde(skip)]
+    pub rating: Option<Rating>,
+    /// Any field that isn't modeled above, kept as sent by the API.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl SitePost {
+    pub fn md5(&self) -> Option<&str> {
+        self.md5.as_deref().or(self.hash.as_deref())
+    }
+}
+
+impl From<SitePost> for BooruPost {
+    fn from(post: SitePost) -> Self {
+        let md5 = post.md5().map(ToString::to_string);
+
+        Self {
+            id: post.id,
+            created_at: post.created_at,
+            score: post.score,
+            width: post.width,
+            height: post.height,
+            md5,
+            file_url: post.file_url,
+            tags: post.tags,
+            image: post.image,
+            source: post.source,
+            rating: post.rating,
+        }
+    }
+}
+
+impl WithId for SitePost {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+/// The JSON answers of the API, depending on the site.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum JsonPosts {
+    List(Vec<SitePost>),
+    Wrapped {
+        #[serde(default)]
+        post: Vec<SitePost>,
+    },
+}
+
+impl Default for JsonPosts {
+    fn default() -> Self {
+        Self::List(Vec::new())
+    }
+}
+
+impl From<JsonPosts> for Vec<SitePost> {
+    fn from(posts: JsonPosts) -> Self {
+        match posts {
+            JsonPosts::List(posts) | JsonPosts::Wrapped { post: posts } => posts,
+        }
+    }
+}
+
+/// Reads the attributes of the `<post .../>` elements of an XML answer, `None` when it isn't
+/// one.
+///
+/// This isn't an XML parser, it only knows enough about the format to get the attributes out of
+/// the posts, which is all the API sends.
+fn parse_xml(body: &str) -> Option<Vec<Map<String, Value>>> {
+    if !body.contains("<posts") {
+        return None;
+    }
+
+    let mut posts = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("<post ") {
+        rest = &rest[start + "<post ".len()..];
+        let end = element_end(rest)?;
+        let element = rest[..end].trim_end_matches('/');
+        rest = &rest[end..];
+
+        posts.push(
+            xml_attributes(element)
+                .map(|(k, v)| (k.to_string(), Value::String(unescape(v))))
+                .collect(),
+        );
+    }
+
+    Some(posts)
+}
+
+/// Where the element `rest` starts with ends, skipping the `>` inside attribute values.
+fn element_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+
+    rest.char_indices()
+        .find(|&(_, c)| match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                false
+            }
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                false
+            }
+            None => c == '>',
+        })
+        .map(|(i, _)| i)
+}
+
+/// The `name="value"` pairs of an element.
+fn xml_attributes(element: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = element;
+
+    std::iter::from_fn(move || {
+        let (name, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let quote = after.chars().next()?;
+        let after = &after[quote.len_utf8()..];
+        let end = after.find(quote)?;
+
+        rest = &after[end + quote.len_utf8()..];
+        Some((name.trim(), &after[..end]))
+    })
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
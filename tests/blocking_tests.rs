@@ -0,0 +1,46 @@
+#[cfg(all(test, feature = "blocking", feature = "gelbooru"))]
+mod blocking {
+    use std::future::{ready, Ready};
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        blocking::BlockingQueryDispatcher,
+        gelbooru::client::GelbooruClient,
+        generic::client::{BooruOption, GenericClient},
+        shared::{
+            client::WithClientBuilder,
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+
+    const POSTS: &str = r#"{"post": [{"id": 1, "rating": "general"}]}"#;
+
+    fn posts(_: HttpRequest) -> Ready<Result<HttpResponse, Error>> {
+        ready(Ok(HttpResponse::new(StatusCode::OK, POSTS)))
+    }
+
+    #[test]
+    fn get_posts_without_a_runtime() {
+        let posts = GelbooruClient::builder()
+            .no_rate_limit()
+            .transport(posts)
+            .query(|q| q.tag("kafuu_chino"))
+            .get_blocking()
+            .unwrap();
+
+        assert_eq!(1, posts[0].id);
+    }
+
+    #[test]
+    fn generic_client_without_a_runtime() {
+        let mut client = GenericClient::new();
+        client.gelbooru.no_rate_limit().transport(posts);
+
+        let posts = client
+            .get_blocking(&GenericClient::query(), BooruOption::Gelbooru)
+            .unwrap();
+
+        assert_eq!(1, posts[0].id);
+    }
+}
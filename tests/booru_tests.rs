@@ -0,0 +1,151 @@
+#[cfg(all(test, feature = "danbooru"))]
+mod booru {
+    use std::sync::Arc;
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        generic::{
+            booru::Booru,
+            client::{BooruOption, GenericClient},
+            AutoCompleteItem, BooruPost, Rating,
+        },
+        shared::{
+            client::ClientQueryBuilder,
+            transport::{BoxFuture, HttpRequest, HttpResponse},
+            Error, Tag,
+        },
+    };
+
+    /// Answers every search with one post per tag, numbered from 1.
+    struct TagBooru;
+
+    fn post(id: u32, tags: &str) -> BooruPost {
+        BooruPost {
+            id,
+            created_at: None,
+            score: 0,
+            width: 0,
+            height: 0,
+            md5: None,
+            file_url: None,
+            tags: tags.to_string(),
+            image: None,
+            source: None,
+            rating: Some(Rating::General),
+        }
+    }
+
+    impl Booru for TagBooru {
+        fn name(&self) -> &str {
+            "tags"
+        }
+
+        fn get<'a>(
+            &'a self,
+            query: &'a ClientQueryBuilder<GenericClient>,
+        ) -> BoxFuture<'a, Result<Vec<BooruPost>, Error>> {
+            let posts = query
+                .tags
+                .0
+                .iter()
+                .filter_map(|tag| match tag {
+                    Tag::Plain(tag) => Some(tag),
+                    _ => None,
+                })
+                .zip(1..)
+                .map(|(tag, id)| post(id, tag))
+                .collect();
+
+            Box::pin(async move { Ok(posts) })
+        }
+
+        fn get_by_id(&self, id: u32) -> BoxFuture<'_, Result<Option<BooruPost>, Error>> {
+            Box::pin(async move { Ok(Some(post(id, ""))) })
+        }
+
+        fn get_autocomplete<'a>(
+            &'a self,
+            _query: &'a ClientQueryBuilder<GenericClient>,
+            input: String,
+        ) -> BoxFuture<'a, Result<Vec<AutoCompleteItem>, Error>> {
+            Box::pin(async move {
+                Ok(vec![AutoCompleteItem {
+                    value: input.clone(),
+                    label: input,
+                }])
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn registered_boorus_are_queried_by_name() {
+        let client = GenericClient::new();
+        client.register("tags", TagBooru);
+
+        let booru = BooruOption::Custom("tags".to_string());
+        let query = GenericClient::query().tag("a").tag("b").to_owned();
+
+        let posts = client.get(&query, booru.clone()).await.unwrap();
+        assert_eq!(
+            vec!["a", "b"],
+            posts.iter().map(|p| &p.tags).collect::<Vec<_>>()
+        );
+
+        let post = client.get_by_id(7, booru.clone()).await.unwrap().unwrap();
+        assert_eq!(7, post.id);
+
+        let items = client.get_autocomplete(&query, booru, "ka").await.unwrap();
+        assert_eq!("ka", items[0].value);
+
+        assert_eq!(vec!["tags"], client.registry.names());
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_registry() {
+        let client = GenericClient::new();
+        let clone = client.clone();
+        client.register("tags", TagBooru);
+
+        assert!(clone
+            .get_by_id(1, BooruOption::Custom("tags".to_string()))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn unknown_names_are_an_error() {
+        let result = GenericClient::new()
+            .get_by_id(1, BooruOption::Custom("nowhere".to_string()))
+            .await;
+
+        assert!(matches!(result, Err(Error::UnknownBooru(name)) if name == "nowhere"));
+    }
+
+    #[tokio::test]
+    async fn builders_are_boorus() {
+        let mut client = GenericClient::new();
+        client
+            .danbooru
+            .default_url("http://localhost:3000")
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async {
+                Ok(HttpResponse::new(StatusCode::OK, r#"[{"id": 1}]"#))
+            });
+
+        let builtin = client.booru(&BooruOption::Danbooru).unwrap();
+        assert_eq!("danbooru", builtin.name());
+
+        // A builder can be registered under another name, e.g. a second instance of a booru.
+        let mirror: Arc<dyn Booru> = Arc::new(client.danbooru.clone());
+        client.registry.register_arc("mirror", mirror);
+
+        let posts = client
+            .get(
+                &GenericClient::query(),
+                BooruOption::Custom("mirror".to_string()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(1, posts[0].id);
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(all(test, feature = "gelbooru"))]
+mod cache {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            cache::{Cache, CacheStore, CachedResponse, DiskCache},
+            client::{ClientBuilder, QueryDispatcher, WithClientBuilder},
+            transport::{HttpRequest, HttpResponse},
+        },
+    };
+
+    const POSTS: &str = r#"{"post": [{"id": 1, "rating": "general"}]}"#;
+
+    fn counting(
+        status: StatusCode,
+        cache: Cache,
+        calls: Arc<AtomicUsize>,
+    ) -> ClientBuilder<GelbooruClient> {
+        GelbooruClient::builder()
+            .no_rate_limit()
+            .cache(cache)
+            .transport(move |_: HttpRequest| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(HttpResponse::new(status, POSTS)) }
+            })
+            .to_owned()
+    }
+
+    #[tokio::test]
+    async fn repeated_search_is_served_from_memory() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let builder = counting(StatusCode::OK, Cache::memory(16), calls.clone());
+
+        let first = builder
+            .query(|q| q.tag("kafuu_chino").tag("maid"))
+            .get()
+            .await;
+        let second = builder
+            .query(|q| q.tag("maid").tag("kafuu_chino"))
+            .get()
+            .await;
+
+        assert_eq!(1, first.unwrap().len());
+        assert_eq!(1, second.unwrap().len());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn bypass_hits_the_api() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let builder = counting(StatusCode::OK, Cache::memory(16), calls.clone());
+
+        builder.query(|q| q.tag("kafuu_chino")).get().await.unwrap();
+        builder
+            .query(|q| q.tag("kafuu_chino"))
+            .bypass_cache()
+            .get()
+            .await
+            .unwrap();
+
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn failures_are_not_cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let builder = counting(StatusCode::BAD_GATEWAY, Cache::memory(16), calls.clone());
+
+        assert!(builder.dispatch().get().await.is_err());
+        assert!(builder.dispatch().get().await.is_err());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn disk_cache_survives_new_builders() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rusty-booru-cache-{nanos}"));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..2 {
+            let post = counting(StatusCode::OK, Cache::disk(&dir).unwrap(), calls.clone())
+                .dispatch()
+                .get_by_id(1)
+                .await;
+
+            assert_eq!(1, post.unwrap().unwrap().id);
+        }
+
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_disk_writes_leave_one_entry_without_the_key() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rusty-booru-cache-{nanos}"));
+        let store = Arc::new(DiskCache::new(&dir).unwrap());
+        let key = "https://gelbooru.com/index.php?api_key=hunter2&page=dapi";
+
+        let writers = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                thread::spawn(move || {
+                    for _ in 0..20 {
+                        let response = HttpResponse::new(StatusCode::OK, POSTS);
+                        store.put(key, CachedResponse::new(&response, Duration::from_secs(60)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(POSTS.as_bytes(), store.get(key).unwrap().body);
+
+        let files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(1, files.len(), "{files:?}");
+        let contents = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(!contents.contains("hunter2"));
+        assert!(!contents.contains("gelbooru.com"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+//! Fixtures shared by the tests of the generic client.
+
+// Every test crate only uses some of them.
+#![allow(dead_code)]
+
+use std::{
+    future::pending,
+    sync::{Arc, Mutex},
+};
+
+use rusty_booru::{
+    generic::{
+        booru::Booru,
+        client::{BooruOption, GenericClient},
+        matching::same_source,
+        AutoCompleteItem, BooruPost, Rating,
+    },
+    shared::{client::ClientQueryBuilder, transport::BoxFuture, Error, Tag},
+};
+
+/// A booru holding `posts` in memory, recording the tags it was asked for.
+#[derive(Clone, Default)]
+pub struct FakeBooru {
+    pub posts: Vec<BooruPost>,
+    /// Keep the posts matching the plain tags of a search, the `md5:` and `source:` metatags
+    /// included, rather than answering every search with all of them.
+    pub searchable: bool,
+    /// Never answer a search.
+    pub hang: bool,
+    /// Tags of every search, with `-` in front of blacklisted ones.
+    pub asked: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeBooru {
+    /// Answers every search with `posts`.
+    pub fn new(posts: Vec<BooruPost>) -> Self {
+        Self {
+            posts,
+            ..Self::default()
+        }
+    }
+
+    /// Answers searches with the posts of `posts` they match.
+    pub fn searchable(posts: Vec<BooruPost>) -> Self {
+        Self {
+            searchable: true,
+            ..Self::new(posts)
+        }
+    }
+
+    pub fn hanging() -> Self {
+        Self {
+            hang: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn asked(&self) -> Vec<String> {
+        self.asked.lock().unwrap().clone()
+    }
+
+    fn matches(post: &BooruPost, tag: &str) -> bool {
+        if let Some(md5) = tag.strip_prefix("md5:") {
+            return post.md5.as_deref() == Some(md5);
+        }
+        if let Some(source) = tag.strip_prefix("source:") {
+            return post
+                .source
+                .as_deref()
+                .is_some_and(|s| s.split_whitespace().any(|s| same_source(s, source)));
+        }
+        post.tags.split_whitespace().any(|t| t == tag)
+    }
+}
+
+impl Booru for FakeBooru {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    fn get<'a>(
+        &'a self,
+        query: &'a ClientQueryBuilder<GenericClient>,
+    ) -> BoxFuture<'a, Result<Vec<BooruPost>, Error>> {
+        self.asked
+            .lock()
+            .unwrap()
+            .extend(query.tags.0.iter().map(|tag| match tag {
+                Tag::Plain(tag) => tag.clone(),
+                Tag::Blacklist(tag) => format!("-{tag}"),
+                Tag::Rating(rating) => format!("rating:{rating}"),
+                Tag::Sort(sort) => format!("order:{sort}"),
+            }));
+
+        let posts = self
+            .posts
+            .iter()
+            .filter(|post| {
+                !self.searchable
+                    || query.tags.0.iter().all(|tag| match tag {
+                        Tag::Plain(tag) => Self::matches(post, tag),
+                        _ => true,
+                    })
+            })
+            .cloned()
+            .collect();
+
+        Box::pin(async move {
+            if self.hang {
+                pending::<()>().await;
+            }
+            Ok(posts)
+        })
+    }
+
+    fn get_by_id(&self, id: u32) -> BoxFuture<'_, Result<Option<BooruPost>, Error>> {
+        let post = self.posts.iter().find(|post| post.id == id).cloned();
+        Box::pin(async move { Ok(post) })
+    }
+
+    /// Completes `input` with the tags of the posts.
+    fn get_autocomplete<'a>(
+        &'a self,
+        _query: &'a ClientQueryBuilder<GenericClient>,
+        input: String,
+    ) -> BoxFuture<'a, Result<Vec<AutoCompleteItem>, Error>> {
+        let mut tags = self
+            .posts
+            .iter()
+            .flat_map(|post| post.tags.split_whitespace())
+            .filter(|tag| tag.starts_with(&input))
+            .collect::<Vec<_>>();
+        tags.sort();
+        tags.dedup();
+
+        let items = tags
+            .into_iter()
+            .map(|tag| AutoCompleteItem {
+                value: tag.to_string(),
+                label: tag.to_string(),
+            })
+            .collect();
+        Box::pin(async move { Ok(items) })
+    }
+}
+
+/// A general 800x600 post with nothing else to it.
+pub fn post(id: u32) -> BooruPost {
+    BooruPost {
+        id,
+        created_at: None,
+        score: 0,
+        width: 800,
+        height: 600,
+        md5: None,
+        file_url: None,
+        tags: String::new(),
+        image: None,
+        source: None,
+        rating: Some(Rating::General),
+    }
+}
+
+/// A post tagged `tags`.
+pub fn tagged(id: u32, tags: &str) -> BooruPost {
+    BooruPost {
+        tags: tags.to_string(),
+        ..post(id)
+    }
+}
+
+/// A client with `boorus` registered under their names.
+pub fn client<'a>(boorus: impl IntoIterator<Item = (&'a str, FakeBooru)>) -> GenericClient {
+    let client = GenericClient::new();
+    for (name, booru) in boorus {
+        client.register(name, booru);
+    }
+    client
+}
+
+pub fn boorus(names: &[&str]) -> Vec<BooruOption> {
+    names
+        .iter()
+        .map(|name| BooruOption::Custom(name.to_string()))
+        .collect()
+}
@@ -0,0 +1,85 @@
+#[cfg(all(test, feature = "gelbooru"))]
+mod conditional {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use reqwest::{
+        header::{HeaderValue, ETAG, IF_NONE_MATCH},
+        StatusCode,
+    };
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            cache::{Cache, CacheTtl},
+            client::{ClientBuilder, QueryDispatcher, WithClientBuilder},
+            transport::{HttpRequest, HttpResponse},
+        },
+    };
+
+    const POSTS: &str = r#"{"post": [{"id": 1, "rating": "general"}]}"#;
+
+    /// Answers `304` whenever the request carries the etag it handed out.
+    fn etag_server(calls: Arc<AtomicUsize>) -> ClientBuilder<GelbooruClient> {
+        GelbooruClient::builder()
+            .no_rate_limit()
+            .revalidate()
+            .transport(move |request: HttpRequest| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let unchanged = request
+                    .headers
+                    .get(IF_NONE_MATCH)
+                    .is_some_and(|v| v == "\"v1\"");
+
+                async move {
+                    Ok(match unchanged {
+                        true => HttpResponse::new(StatusCode::NOT_MODIFIED, ""),
+                        false => HttpResponse::new(StatusCode::OK, POSTS)
+                            .header(ETAG, HeaderValue::from_static("\"v1\"")),
+                    })
+                }
+            })
+            .to_owned()
+    }
+
+    #[tokio::test]
+    async fn unchanged_search_reports_none() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let builder = etag_server(calls.clone());
+        let query = builder.query(|q| q.tag("kafuu_chino"));
+
+        assert_eq!(1, query.get_if_changed().await.unwrap().unwrap().len());
+        assert!(query.get_if_changed().await.unwrap().is_none());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn plain_calls_without_cache_are_not_conditional() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let builder = etag_server(calls.clone());
+
+        assert_eq!(1, builder.dispatch().get().await.unwrap().len());
+        assert_eq!(1, builder.dispatch().get().await.unwrap().len());
+        assert!(builder.dispatch().get_if_changed().await.unwrap().is_none());
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn not_modified_is_answered_from_the_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let expired = CacheTtl {
+            posts: std::time::Duration::from_nanos(1),
+            ..Default::default()
+        };
+        let builder = etag_server(calls.clone())
+            .cache(Cache::memory(16).ttl(expired))
+            .to_owned();
+
+        builder.dispatch().get().await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+
+        assert_eq!(1, builder.dispatch().get().await.unwrap().len());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+}
@@ -1,13 +1,36 @@
-#[cfg(test)]
+#[cfg(all(test, feature = "danbooru"))]
 mod danbooru {
+    use reqwest::StatusCode;
     use rusty_booru::{
-        danbooru::{client::DanbooruClient, DanbooruRating},
+        danbooru::{
+            client::{DanbooruClient, DanbooruError},
+            DanbooruPost, DanbooruRating,
+        },
         shared::{
             client::{QueryDispatcher, WithClientBuilder},
-            Sort,
-        }, generic::client::GenericClient,
+            retry::RetryPolicy,
+            transport::{HttpRequest, HttpResponse},
+            Error, Sort,
+        },
     };
 
+    /// What a search fails with when Danbooru answers with `status` and `body`.
+    async fn fail_with(status: StatusCode, body: &'static str) -> Error {
+        DanbooruClient::builder()
+            .no_rate_limit()
+            .retry(RetryPolicy::none())
+            .transport(move |_: HttpRequest| async move { Ok(HttpResponse::new(status, body)) })
+            .dispatch()
+            .get()
+            .await
+            .unwrap_err()
+    }
+
+    const TAG_LIMIT: &str = concat!(
+        r#"{"success": false, "error": "PostQuery::TagLimitError","#,
+        r#" "message": "You cannot search for more than 2 tags at a time."}"#
+    );
+
     #[tokio::test]
     async fn get_posts_with_tag() {
         let posts = DanbooruClient::builder()
@@ -110,6 +133,95 @@ mod danbooru {
         assert!(posts.is_err());
     }
 
+    #[test]
+    fn decode_known_error() {
+        let error = DanbooruError::new(
+            "PostQuery::TagLimitError".to_string(),
+            "You cannot search for more than 2 tags at a time.".to_string(),
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY,
+        );
+
+        assert!(matches!(error, DanbooruError::TagLimitError { .. }));
+        assert_eq!("PostQuery::TagLimitError", error.name());
+        assert_eq!(422, error.status().as_u16());
+        assert_eq!(
+            "You cannot search for more than 2 tags at a time.",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn decode_unknown_error() {
+        let error = DanbooruError::new(
+            "Danbooru::SomethingNew".to_string(),
+            "Something went wrong".to_string(),
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+        );
+
+        assert!(
+            matches!(&error, DanbooruError::Other { name, .. } if name == "Danbooru::SomethingNew")
+        );
+        assert_eq!("Something went wrong", error.message());
+    }
+
+    #[tokio::test]
+    async fn decode_error_bodies() {
+        let error = fail_with(StatusCode::UNPROCESSABLE_ENTITY, TAG_LIMIT).await;
+        assert!(matches!(
+            &error,
+            Error::Danbooru(DanbooruError::TagLimitError { message, status })
+                if message.starts_with("You cannot") && *status == StatusCode::UNPROCESSABLE_ENTITY
+        ));
+
+        let error = fail_with(
+            StatusCode::FORBIDDEN,
+            r#"{"success": false, "error": "Danbooru::SomethingNew", "message": "No"}"#,
+        )
+        .await;
+        assert!(matches!(
+            error,
+            Error::Danbooru(DanbooruError::Other { name, .. }) if name == "Danbooru::SomethingNew"
+        ));
+
+        // Without a body to decode, the status decides.
+        let error = fail_with(StatusCode::FORBIDDEN, "<html>Cloudflare</html>").await;
+        assert!(matches!(error, Error::Forbidden));
+    }
+
+    #[tokio::test]
+    async fn rate_limits_and_server_errors_ignore_the_body() {
+        let error = fail_with(StatusCode::TOO_MANY_REQUESTS, TAG_LIMIT).await;
+        assert!(matches!(error, Error::RateLimited { .. }));
+
+        let error = fail_with(StatusCode::SERVICE_UNAVAILABLE, TAG_LIMIT).await;
+        assert!(matches!(
+            error,
+            Error::Server { status } if status == StatusCode::SERVICE_UNAVAILABLE
+        ));
+    }
+
+    #[test]
+    fn deserialize_lenient_post() {
+        let post: DanbooruPost = serde_json::from_str(
+            r#"{
+                "id": 9423,
+                "score": "-2",
+                "approver_id": null,
+                "rating": "g",
+                "md5": "15a1b49c26f5c684807a2f0b838f9e4c",
+                "image_width": 640,
+                "is_deleted": false,
+                "media_asset": {"id": 1}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(-2, post.score);
+        assert_eq!(None, post.approver_id);
+        assert_eq!("", post.tag_string);
+        assert!(post.extra.contains_key("media_asset"));
+    }
+
     #[test]
     fn parse_rating_tags() {
         assert_eq!("explicit", DanbooruRating::Explicit.to_string());
@@ -0,0 +1,127 @@
+#[cfg(all(test, feature = "gelbooru"))]
+mod errors {
+    use std::time::{Duration, SystemTime};
+
+    use reqwest::{
+        header::{HeaderValue, RETRY_AFTER},
+        StatusCode,
+    };
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            cache::Cache,
+            client::{QueryDispatcher, WithClientBuilder},
+            retry::RetryPolicy,
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+
+    /// What a search fails with when the API answers with `response`, without retries.
+    async fn fail_with(response: HttpResponse) -> Error {
+        GelbooruClient::builder()
+            .no_rate_limit()
+            .retry(RetryPolicy::none())
+            .transport(move |_: HttpRequest| {
+                let response = response.clone();
+                async move { Ok(response) }
+            })
+            .dispatch()
+            .get()
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn statuses_are_mapped_to_errors() {
+        let cases = [
+            (StatusCode::NOT_FOUND, "not_found"),
+            (StatusCode::UNAUTHORIZED, "unauthorized"),
+            (StatusCode::FORBIDDEN, "forbidden"),
+            (StatusCode::BAD_REQUEST, "invalid_query"),
+            (StatusCode::UNPROCESSABLE_ENTITY, "invalid_query"),
+            (StatusCode::TOO_MANY_REQUESTS, "rate_limited"),
+            (StatusCode::INTERNAL_SERVER_ERROR, "server"),
+            (StatusCode::SERVICE_UNAVAILABLE, "server"),
+            (StatusCode::IM_A_TEAPOT, "unexpected_status"),
+        ];
+
+        for (status, kind) in cases {
+            let error = fail_with(HttpResponse::new(status, "no")).await;
+            assert_eq!(kind, error.kind(), "{status}");
+        }
+    }
+
+    #[tokio::test]
+    async fn bodies_are_kept_as_snippets() {
+        let error = fail_with(HttpResponse::new(StatusCode::BAD_REQUEST, "  tag limit  ")).await;
+        assert!(matches!(error, Error::InvalidQuery(message) if message == "tag limit"));
+
+        let long = "x".repeat(1000);
+        let error = fail_with(HttpResponse::new(StatusCode::IM_A_TEAPOT, long)).await;
+        assert!(matches!(error, Error::UnexpectedStatus { snippet, .. } if snippet.len() == 259));
+    }
+
+    #[tokio::test]
+    async fn retry_after_is_read_as_seconds_or_date() {
+        let error = fail_with(
+            HttpResponse::new(StatusCode::TOO_MANY_REQUESTS, "")
+                .header(RETRY_AFTER, HeaderValue::from_static("12")),
+        )
+        .await;
+        assert!(matches!(
+            error,
+            Error::RateLimited { retry_after: Some(d) } if d == Duration::from_secs(12)
+        ));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        let error = fail_with(
+            HttpResponse::new(StatusCode::TOO_MANY_REQUESTS, "")
+                .header(RETRY_AFTER, HeaderValue::from_str(&date).unwrap()),
+        )
+        .await;
+        assert!(matches!(
+            error,
+            Error::RateLimited { retry_after: Some(d) } if d > Duration::from_secs(100)
+        ));
+
+        let error = fail_with(
+            HttpResponse::new(StatusCode::TOO_MANY_REQUESTS, "")
+                .header(RETRY_AFTER, HeaderValue::from_static("soon")),
+        )
+        .await;
+        assert!(matches!(error, Error::RateLimited { retry_after: None }));
+    }
+
+    #[tokio::test]
+    async fn credentials_are_kept_out_of_errors_and_cache_keys() {
+        let error = GelbooruClient::builder()
+            .no_rate_limit()
+            .query_param("api_key", "hunter2")
+            .transport(|_: HttpRequest| async {
+                Ok(HttpResponse::new(StatusCode::OK, r#"{"post": 5}"#))
+            })
+            .dispatch()
+            .get()
+            .await
+            .unwrap_err();
+
+        assert!(matches!(&error, Error::Decode { url, .. } if url.contains("api_key=REDACTED")));
+        assert!(!error.to_string().contains("hunter2"));
+
+        let request = |api_key| {
+            HttpRequest::get("https://gelbooru.com/index.php")
+                .query([("api_key", api_key), ("tags", "kafuu_chino")])
+        };
+        let key = Cache::key(&request("hunter2"));
+        assert!(!key.contains("hunter2"));
+        assert_ne!(key, Cache::key(&request("hunter3")));
+        assert_ne!(
+            key,
+            Cache::key(
+                &HttpRequest::get("https://gelbooru.com/index.php")
+                    .query([("tags", "kafuu_chino")])
+            )
+        );
+    }
+}
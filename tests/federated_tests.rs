@@ -0,0 +1,173 @@
+mod common;
+
+#[cfg(test)]
+mod federated {
+    use std::time::Duration;
+
+    use rusty_booru::{
+        generic::{
+            client::{BooruOption, GenericClient},
+            federated::{Federation, Order, SourcedPost},
+            BooruPost,
+        },
+        shared::Error,
+    };
+
+    use crate::common::{boorus, FakeBooru};
+
+    fn post(id: u32, score: i64, md5: &str, created_at: &str) -> BooruPost {
+        BooruPost {
+            created_at: Some(created_at.to_string()),
+            score,
+            md5: Some(md5.to_string()),
+            ..crate::common::post(id)
+        }
+    }
+
+    fn client() -> GenericClient {
+        crate::common::client([
+            (
+                "a",
+                FakeBooru::new(vec![
+                    post(1, 5, "aa", "2024-01-01T00:00:00.000+00:00"),
+                    post(2, 1, "same", "2024-01-03T00:00:00Z"),
+                ]),
+            ),
+            (
+                "b",
+                FakeBooru::new(vec![
+                    post(3, 9, "SAME", "Tue Jan 02 00:00:00 +0000 2024"),
+                    post(4, 3, "bb", "not a date"),
+                ]),
+            ),
+            ("slow", FakeBooru::hanging()),
+        ])
+    }
+
+    fn ids(posts: &[SourcedPost]) -> Vec<u32> {
+        posts.iter().map(|sourced| sourced.post.id).collect()
+    }
+
+    #[tokio::test]
+    async fn posts_are_interleaved_without_duplicates() {
+        let found = client()
+            .search(
+                &GenericClient::query(),
+                boorus(&["a", "b"]),
+                &Federation::new(),
+            )
+            .await;
+
+        // 2 has the same md5 as 3, which came first.
+        assert_eq!(vec![1, 3, 4], ids(&found.posts));
+        assert_eq!(BooruOption::Custom("b".into()), found.posts[1].booru);
+        assert!(found.is_complete());
+    }
+
+    #[tokio::test]
+    async fn posts_can_be_sorted() {
+        let client = client();
+        let query = GenericClient::query();
+
+        let by_score = Federation::new().order(Order::Score);
+        let found = client.search(&query, boorus(&["a", "b"]), &by_score).await;
+        assert_eq!(vec![3, 1, 4], ids(&found.posts));
+
+        let by_date = Federation::new().order(Order::Date);
+        let found = client.search(&query, boorus(&["a", "b"]), &by_date).await;
+        assert_eq!(vec![2, 1, 4], ids(&found.posts));
+    }
+
+    #[tokio::test]
+    async fn slow_and_failing_boorus_only_lose_their_results() {
+        let federation = Federation::new().timeout(Some(Duration::from_millis(50)));
+        let found = client()
+            .search(
+                &GenericClient::query(),
+                boorus(&["a", "slow", "missing"]),
+                &federation,
+            )
+            .await;
+
+        assert_eq!(vec![1, 2], ids(&found.posts));
+        assert!(matches!(
+            &found.failures[..],
+            [(_, Error::Timeout(_)), (_, Error::UnknownBooru(_))]
+        ));
+    }
+
+    #[tokio::test]
+    async fn dates_of_every_booru_are_read() {
+        let client = crate::common::client([(
+            "dates",
+            FakeBooru::new(vec![
+                post(1, 0, "1", "2024-01-31T12:00:00.000-05:00"),
+                post(2, 0, "2", "2024-01-31 17:00:01"),
+                post(3, 0, "3", "Wed Jan 31 11:59:59 -0500 2024"),
+                post(4, 0, "4", "yesterday"),
+            ]),
+        )]);
+
+        let by_date = Federation::new().order(Order::Date);
+        let found = client
+            .search(&GenericClient::query(), boorus(&["dates"]), &by_date)
+            .await;
+        assert_eq!(vec![2, 1, 3, 4], ids(&found.posts));
+    }
+
+    #[tokio::test]
+    async fn impossible_dates_are_not_read() {
+        let dates = [
+            "2024-13-01T00:00:00Z",
+            "2024-02-30T00:00:00Z",
+            "2023-02-29 00:00:00",
+            "2024-01-01T24:00:00Z",
+            "2024-01-01T12:60:00Z",
+            "2024-01-01T12:00:00+25:00",
+            "2024-1-01T12:00:00Z",
+            "Wed Jan 32 12:00:00 -0500 2024",
+            "Wed Jan 31 12:00:00 -0560 2024",
+        ];
+        let mut posts = dates
+            .iter()
+            .enumerate()
+            .map(|(i, date)| post(i as u32, 0, &i.to_string(), date))
+            .collect::<Vec<_>>();
+        posts.push(post(99, 0, "99", "1970-01-01T00:00:00Z"));
+
+        let client = crate::common::client([("dates", FakeBooru::new(posts))]);
+        let by_date = Federation::new().order(Order::Date);
+        let found = client
+            .search(&GenericClient::query(), boorus(&["dates"]), &by_date)
+            .await;
+
+        // Only the epoch could be read, the others keep their order behind it.
+        assert_eq!(99, found.posts[0].post.id);
+        assert_eq!((0..9).collect::<Vec<_>>(), ids(&found.posts[1..]));
+    }
+
+    #[tokio::test]
+    async fn dates_with_other_characters_in_the_zone_are_not_read() {
+        let dates = [
+            "Wed Jan 31 12:00:00 é 2024",
+            "Wed Jan 31 12:00:00 +é:00 2024",
+            "Wed Jan 31 12:00:00 +0é0 2024",
+            "2024-01-31T12:00:00+05:é",
+        ];
+        let mut posts = dates
+            .iter()
+            .enumerate()
+            .map(|(i, date)| post(i as u32, 0, &i.to_string(), date))
+            .collect::<Vec<_>>();
+        posts.push(post(99, 0, "99", "1970-01-01T00:00:00Z"));
+
+        let client = crate::common::client([("zones", FakeBooru::new(posts))]);
+        let by_date = Federation::new().order(Order::Date);
+        let found = client
+            .search(&GenericClient::query(), boorus(&["zones"]), &by_date)
+            .await;
+
+        assert_eq!(99, found.posts[0].post.id);
+        assert_eq!((0..4).collect::<Vec<_>>(), ids(&found.posts[1..]));
+    }
+}
@@ -1,6 +1,8 @@
+#[cfg(feature = "gelbooru")]
 mod gelbooru {
     use rusty_booru::{
-        gelbooru::{client::GelbooruClient, GelbooruRating},
+        gelbooru::{client::GelbooruClient, GelbooruRating, GelbooruResponse},
+        generic::BooruPost,
         shared::{
             client::{QueryDispatcher, WithClientBuilder},
             Sort,
@@ -94,6 +96,67 @@ mod gelbooru {
         );
     }
 
+    #[test]
+    fn deserialize_lenient_post() {
+        let response: GelbooruResponse = serde_json::from_str(
+            r#"{
+                "@attributes": {"limit": 1, "offset": 0, "count": 1},
+                "post": [{
+                    "id": "7898595",
+                    "created_at": "Sat Oct 15 19:23:44 -0500 2022",
+                    "score": -3,
+                    "width": "1000",
+                    "height": 1400.0,
+                    "md5": "e40b797a0e26755b2c0dd7a34d8c95ce",
+                    "file_url": null,
+                    "tags": "kafuu_chino",
+                    "rating": "general",
+                    "has_notes": "false"
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let post = &response.posts[0];
+        assert_eq!(7898595, post.id);
+        assert_eq!(-3, post.score);
+        assert_eq!(1000, post.width);
+        assert_eq!(1400, post.height);
+        assert_eq!("", post.file_url);
+        assert_eq!("false", post.extra["has_notes"]);
+    }
+
+    #[test]
+    fn missing_fields_are_unset_on_generic_posts() {
+        let response: GelbooruResponse =
+            serde_json::from_str(r#"{"post": [{"id": 1, "md5": "", "file_url": null}]}"#).unwrap();
+
+        let post = BooruPost::from(response.posts[0].clone());
+        assert_eq!(None, post.md5);
+        assert_eq!(None, post.file_url);
+        assert_eq!(None, post.source);
+        assert_eq!(None, post.created_at);
+    }
+
+    #[test]
+    fn unknown_ratings_and_missing_ids_are_not_guessed() {
+        let response: GelbooruResponse =
+            serde_json::from_str(r#"{"post": [{"id": 1, "rating": "r-18g"}, {"id": 2}]}"#).unwrap();
+        assert!(response.posts.iter().all(|post| post.rating.is_none()));
+
+        let result = serde_json::from_str::<GelbooruResponse>(r#"{"post": [{"tags": "a"}]}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn deserialize_response_without_posts() {
+        let response: GelbooruResponse =
+            serde_json::from_str(r#"{"@attributes": {"limit": 100, "offset": 0, "count": 0}}"#)
+                .unwrap();
+
+        assert!(response.posts.is_empty());
+    }
+
     #[test]
     fn parse_rating_tags() {
         assert_eq!("explicit", GelbooruRating::Explicit.to_string());
@@ -1,6 +1,17 @@
-#[cfg(test)]
+#[cfg(all(
+    test,
+    feature = "danbooru",
+    feature = "gelbooru",
+    feature = "safebooru"
+))]
 mod generic {
-    use rusty_booru::generic::client::{BooruOption, GenericClient};
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        generic::client::{BooruOption, GenericClient},
+        shared::transport::{HttpRequest, HttpResponse},
+    };
     use strum::IntoEnumIterator;
 
     #[tokio::test]
@@ -37,4 +48,67 @@ mod generic {
             assert!(!tags.unwrap().is_empty());
         }
     }
+
+    #[tokio::test]
+    async fn configured_builders_are_used() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        let mut client = GenericClient::new();
+        client
+            .danbooru
+            .default_url("http://localhost:3000")
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                recorded.lock().unwrap().push(request.full_url());
+                async move { Ok(HttpResponse::new(StatusCode::OK, r#"[{"id": 1}]"#)) }
+            });
+
+        let query = GenericClient::query()
+            .tag("kafuu_chino")
+            .limit(5)
+            .to_owned();
+        for _ in 0..2 {
+            let posts = client.get(&query, BooruOption::Danbooru).await.unwrap();
+            assert_eq!(1, posts[0].id);
+        }
+
+        assert_eq!(
+            vec!["http://localhost:3000/posts.json?limit=5&tags=kafuu_chino"; 2],
+            *requests.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn every_runtime_gets_its_own_shared_client() {
+        let in_new_runtime = |register: bool| {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async move {
+                    if register {
+                        GenericClient::shared()
+                            .register("per_runtime", GenericClient::new().gelbooru);
+                    }
+
+                    let client = GenericClient::shared();
+                    let reused = Arc::ptr_eq(
+                        &client.gelbooru.transport,
+                        &GenericClient::shared().gelbooru.transport,
+                    );
+                    let registered = client.registry.get("per_runtime").is_some();
+                    (client.gelbooru.transport, reused, registered)
+                })
+        };
+
+        let (first, reused, _) = in_new_runtime(true);
+        assert!(reused);
+
+        let (second, _, registered) = in_new_runtime(false);
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert!(registered);
+
+        // The clients of both runtimes went with them.
+        assert_eq!(1, Arc::strong_count(&first));
+        assert_eq!(1, Arc::strong_count(&second));
+    }
 }
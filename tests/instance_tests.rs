@@ -0,0 +1,224 @@
+#[cfg(all(test, feature = "danbooru"))]
+mod instance {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use futures_util::future::join_all;
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        danbooru::{
+            instance::{Capabilities, Instance, InstanceConfig, Quirks, RatingScheme},
+            DanbooruRating,
+        },
+        generic::{
+            client::{BooruOption, GenericClient},
+            Rating,
+        },
+        shared::{
+            retry::RetryPolicy,
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+
+    /// A legacy fork allowing 2 tags and no autocompletion, recording the urls it was asked for.
+    fn fork(config: InstanceConfig) -> (Instance, Arc<Mutex<Vec<String>>>) {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = urls.clone();
+
+        let mut instance = Instance::new(config);
+        instance
+            .builder
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                recorded.lock().unwrap().push(request.full_url());
+
+                let (status, body) = match request.url.rsplit('/').next().unwrap() {
+                    "profile.json" => (StatusCode::OK, r#"{"id": 0, "tag_query_limit": "2"}"#),
+                    "posts.json" => (StatusCode::OK, r#"[{"id": 1, "rating": "s"}]"#),
+                    _ => (StatusCode::NOT_FOUND, ""),
+                };
+                async move { Ok(HttpResponse::new(status, body)) }
+            });
+
+        (instance, urls)
+    }
+
+    fn legacy() -> InstanceConfig {
+        let mut config = InstanceConfig::new("fork", "http://localhost:3000");
+        config.quirks.ratings = RatingScheme::Legacy;
+        config.quirks.max_limit = Some(20);
+        config
+    }
+
+    #[tokio::test]
+    async fn instances_are_probed_once() {
+        let (instance, urls) = fork(legacy());
+
+        let query = GenericClient::query()
+            .tag("kafuu_chino")
+            .rating(Rating::General)
+            .limit(50)
+            .to_owned();
+        let posts = instance.get(&query).await.unwrap();
+        instance.get(&query).await.unwrap();
+
+        let urls = urls.lock().unwrap();
+        assert!(urls[0].contains("/profile.json"));
+        assert!(urls[1].contains("/autocomplete.json"));
+        assert_eq!(
+            "http://localhost:3000/posts.json?limit=20&tags=kafuu_chino+rating%3As",
+            urls[2]
+        );
+        assert_eq!(4, urls.len());
+
+        assert!(matches!(posts[0].rating, Some(DanbooruRating::General)));
+    }
+
+    #[tokio::test]
+    async fn detected_limits_are_enforced() {
+        let (instance, urls) = fork(legacy());
+
+        let query = GenericClient::query()
+            .tag("a")
+            .blacklist_tag("b")
+            .tag("c")
+            .rating(Rating::Explicit)
+            .to_owned();
+        let result = instance.get(&query).await;
+        assert!(matches!(result, Err(Error::InvalidQuery(_))));
+
+        let result = instance
+            .get_autocomplete(&GenericClient::query(), "ka")
+            .await;
+        assert!(matches!(result, Err(Error::Unsupported("autocomplete"))));
+
+        // Only the probes were sent.
+        assert_eq!(2, urls.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn declared_quirks_are_used_without_detection() {
+        let mut config = legacy();
+        config.detect = false;
+        config.quirks.tag_limit = Some(1);
+        let (instance, urls) = fork(config);
+
+        let query = GenericClient::query().tag("a").tag("b").to_owned();
+        assert!(matches!(
+            instance.get(&query).await,
+            Err(Error::InvalidQuery(_))
+        ));
+        assert!(urls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn instances_are_usable_through_the_generic_client() {
+        let (instance, _) = fork(legacy());
+
+        let client = GenericClient::new();
+        client.register("fork", instance);
+
+        let post = client
+            .get_by_id(1, BooruOption::Custom("fork".to_string()))
+            .await;
+        // `/posts/1.json` isn't served by the fork above.
+        assert!(matches!(post, Ok(None)));
+    }
+
+    #[test]
+    fn configs_can_be_read_from_serde() {
+        let config: InstanceConfig = serde_json::from_str(
+            r#"{
+                "name": "fork",
+                "url": "http://localhost:3000",
+                "ratings": "legacy",
+                "tag_limit": 6
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(RatingScheme::Legacy, config.quirks.ratings);
+        assert_eq!(Some(6), config.quirks.tag_limit);
+        assert!(config.quirks.autocomplete);
+        assert!(config.detect);
+    }
+
+    /// An instance answering its probes with `profile` and `autocomplete` after a little while,
+    /// counting how many times it was probed.
+    fn slow(profile: StatusCode, autocomplete: StatusCode) -> (Instance, Arc<Mutex<Vec<String>>>) {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = urls.clone();
+
+        let mut instance = Instance::new(legacy());
+        instance
+            .builder
+            .no_rate_limit()
+            .retry(RetryPolicy::none())
+            .transport(move |request: HttpRequest| {
+                let endpoint = request.url.rsplit('/').next().unwrap().to_string();
+                recorded.lock().unwrap().push(endpoint.clone());
+
+                async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    Ok(match endpoint.as_str() {
+                        "profile.json" => HttpResponse::new(profile, "{}"),
+                        "autocomplete.json" => HttpResponse::new(autocomplete, "[]"),
+                        _ => HttpResponse::new(StatusCode::OK, "[]"),
+                    })
+                }
+            });
+
+        (instance, urls)
+    }
+
+    fn probes(urls: &Mutex<Vec<String>>) -> usize {
+        urls.lock()
+            .unwrap()
+            .iter()
+            .filter(|url| *url == "profile.json")
+            .count()
+    }
+
+    #[tokio::test]
+    async fn concurrent_calls_wait_for_the_same_probe() {
+        let (instance, urls) = slow(StatusCode::OK, StatusCode::OK);
+        let query = GenericClient::query();
+
+        for result in join_all((0..4).map(|_| instance.get(&query))).await {
+            result.unwrap();
+        }
+        assert_eq!(1, probes(&urls));
+    }
+
+    #[tokio::test]
+    async fn failed_probes_are_not_retried_right_away() {
+        let (instance, urls) = slow(StatusCode::SERVICE_UNAVAILABLE, StatusCode::OK);
+
+        for _ in 0..3 {
+            assert_eq!(legacy().quirks, instance.quirks().await);
+        }
+        assert_eq!(1, probes(&urls));
+    }
+
+    #[tokio::test]
+    async fn autocomplete_is_only_dropped_when_missing() {
+        let (instance, _) = slow(StatusCode::OK, StatusCode::FORBIDDEN);
+        assert!(instance.quirks().await.autocomplete);
+
+        let (instance, _) = slow(StatusCode::OK, StatusCode::METHOD_NOT_ALLOWED);
+        assert!(!instance.quirks().await.autocomplete);
+
+        let declared = Quirks {
+            autocomplete: false,
+            ..Quirks::default()
+        };
+        let detected = Capabilities {
+            tag_limit: Some(2),
+            autocomplete: true,
+        };
+        assert!(!declared.with(&detected).autocomplete);
+    }
+}
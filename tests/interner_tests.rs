@@ -0,0 +1,46 @@
+#[cfg(all(test, feature = "danbooru"))]
+mod interner {
+    use rusty_booru::{
+        danbooru::DanbooruPost,
+        shared::interner::{InternTags, TagInterner},
+    };
+
+    #[test]
+    fn intern_shares_ids_between_posts() {
+        let interner = TagInterner::new();
+
+        let first = interner.intern_all("1girl kafuu_chino  maid");
+        let second = interner.intern_all("kafuu_chino 1girl");
+
+        assert_eq!(3, interner.len());
+        assert_eq!(first.ids()[0], second.ids()[1]);
+        assert_eq!("1girl kafuu_chino maid", first.to_tag_string(&interner));
+    }
+
+    #[test]
+    fn intern_danbooru_post_by_category() {
+        let interner = TagInterner::new();
+        let post = DanbooruPost {
+            tag_string: "1girl kafuu_chino koi gochuumon_wa_usagi_desu_ka?".to_string(),
+            tag_string_general: "1girl".to_string(),
+            tag_string_artist: "koi".to_string(),
+            tag_string_copyright: "gochuumon_wa_usagi_desu_ka?".to_string(),
+            tag_string_character: "kafuu_chino".to_string(),
+            ..Default::default()
+        };
+
+        let interned = post.intern_tags(&interner);
+
+        assert!(interned.post.tag_string.is_empty());
+        assert!(interned.post.tag_string_general.is_empty());
+        assert_eq!(Some(interned.tags.artist.ids()[0]), interner.get("koi"));
+        assert_eq!(4, interned.tags.all().count());
+
+        let generic = interned.into_generic();
+        assert!(generic.post.tags.is_empty());
+        assert_eq!(
+            "1girl koi gochuumon_wa_usagi_desu_ka? kafuu_chino",
+            generic.tags.to_tag_string(&interner)
+        );
+    }
+}
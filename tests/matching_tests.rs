@@ -0,0 +1,225 @@
+mod common;
+
+#[cfg(test)]
+mod matching {
+    use rusty_booru::{
+        generic::{
+            client::{BooruOption, GenericClient},
+            federated::SourcedPost,
+            matching::{overlap, same_source, Confidence, Matcher},
+            BooruPost,
+        },
+        shared::Error,
+    };
+
+    use crate::common::{boorus, tagged, FakeBooru};
+
+    fn post(id: u32, md5: Option<&str>, source: Option<&str>, tags: &str) -> BooruPost {
+        BooruPost {
+            md5: md5.map(String::from),
+            source: source.map(String::from),
+            ..tagged(id, tags)
+        }
+    }
+
+    fn origin() -> SourcedPost {
+        SourcedPost {
+            booru: BooruOption::Custom("a".into()),
+            post: post(
+                1,
+                Some("abc"),
+                Some("https://www.pixiv.net/artworks/1"),
+                "1girl kafuu_chino gochuumon_wa_usagi_desu_ka? solo",
+            ),
+        }
+    }
+
+    fn client() -> GenericClient {
+        crate::common::client([
+            ("a", FakeBooru::searchable(vec![origin().post])),
+            (
+                "same_file",
+                FakeBooru::searchable(vec![
+                    post(10, Some("abc"), None, "kafuu_chino"),
+                    post(
+                        11,
+                        Some("def"),
+                        Some("https://www.pixiv.net/artworks/1"),
+                        "",
+                    ),
+                ]),
+            ),
+            (
+                "same_source",
+                FakeBooru::searchable(vec![
+                    post(20, Some("def"), Some("pixiv.net/artworks/1/"), "chino"),
+                    post(21, Some("ghi"), Some("https://pixiv.net/artworks/2"), ""),
+                ]),
+            ),
+            (
+                "same_tags",
+                FakeBooru::searchable(vec![
+                    post(
+                        30,
+                        None,
+                        None,
+                        "1girl kafuu_chino gochuumon_wa_usagi_desu_ka?",
+                    ),
+                    post(
+                        31,
+                        None,
+                        None,
+                        "kafuu_chino gochuumon_wa_usagi_desu_ka? hoto_cocoa",
+                    ),
+                    // A page of the same comic.
+                    BooruPost {
+                        width: 600,
+                        ..post(
+                            32,
+                            None,
+                            None,
+                            "1girl kafuu_chino gochuumon_wa_usagi_desu_ka?",
+                        )
+                    },
+                ]),
+            ),
+        ])
+    }
+
+    #[tokio::test]
+    async fn each_booru_is_matched_on_its_best_evidence() {
+        let matches = client()
+            .find_matches(
+                &origin(),
+                boorus(&["same_tags", "same_source", "same_file"]),
+                &Matcher::new(),
+            )
+            .await;
+
+        let links = matches
+            .links
+            .iter()
+            .map(|link| (link.post.post.id, link.confidence))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (10, Confidence::Certain),
+                (20, Confidence::High),
+                (30, Confidence::Low),
+            ],
+            links
+        );
+        assert_eq!(0.75, matches.links[2].overlap);
+        assert_eq!(2, matches.at_least(Confidence::High).count());
+        assert!(matches.is_complete());
+    }
+
+    #[tokio::test]
+    async fn the_origin_is_not_its_own_match() {
+        let matches = client()
+            .find_matches(&origin(), boorus(&["a", "missing"]), &Matcher::new())
+            .await;
+
+        assert!(matches.links.is_empty());
+        assert!(matches!(
+            &matches.failures[..],
+            [(_, Error::UnknownBooru(_))]
+        ));
+    }
+
+    #[tokio::test]
+    async fn weak_tag_overlaps_are_ignored() {
+        let matches = client()
+            .find_matches(
+                &origin(),
+                boorus(&["same_tags"]),
+                &Matcher::new().overlap(0.9),
+            )
+            .await;
+
+        assert!(matches.links.is_empty());
+    }
+
+    #[tokio::test]
+    async fn posts_without_md5_are_not_the_same_file() {
+        // Answers the md5 search with a post it can't tell anything about.
+        let client = crate::common::client([(
+            "no_md5",
+            FakeBooru::new(vec![post(40, None, None, "hoto_cocoa")]),
+        )]);
+
+        let matches = client
+            .find_matches(&origin(), boorus(&["no_md5"]), &Matcher::new())
+            .await;
+        assert!(matches.links.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sources_with_several_urls_are_searched_by_the_first() {
+        let booru = FakeBooru::searchable(vec![post(
+            50,
+            None,
+            Some("https://twitter.com/a/status/2 pixiv.net/artworks/1"),
+            "",
+        )]);
+        let client = crate::common::client([("several", booru.clone())]);
+
+        let origin = SourcedPost {
+            booru: BooruOption::Custom("a".into()),
+            post: post(
+                1,
+                None,
+                Some("https://www.pixiv.net/artworks/1 https://twitter.com/a/status/2"),
+                "",
+            ),
+        };
+        let matches = client
+            .find_matches(&origin, boorus(&["several"]), &Matcher::new())
+            .await;
+
+        assert_eq!(
+            vec!["source:https://www.pixiv.net/artworks/1"],
+            booru.asked()
+        );
+        assert_eq!(Confidence::High, matches.links[0].confidence);
+    }
+
+    #[cfg(feature = "safebooru")]
+    #[tokio::test]
+    async fn safebooru_hashes_are_md5s() {
+        use reqwest::StatusCode;
+        use rusty_booru::shared::transport::{HttpRequest, HttpResponse};
+
+        let mut client = GenericClient::new();
+        client
+            .safebooru
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async {
+                Ok(HttpResponse::new(
+                    StatusCode::OK,
+                    r#"[{"id": 7, "hash": "abc", "image": "abc.jpg"}]"#,
+                ))
+            });
+
+        let matches = client
+            .find_matches(&origin(), [BooruOption::Safebooru], &Matcher::new())
+            .await;
+        assert_eq!(7, matches.links[0].post.post.id);
+        assert_eq!(Confidence::Certain, matches.links[0].confidence);
+    }
+
+    #[test]
+    fn tags_and_sources_are_compared_loosely() {
+        assert_eq!(0.5, overlap("a b C", "c b d"));
+        assert_eq!(0.0, overlap("", ""));
+
+        assert!(same_source(
+            "https://www.Pixiv.net/artworks/1/",
+            "http://pixiv.net/artworks/1"
+        ));
+        assert!(!same_source(
+            "https://pixiv.net/artworks/1",
+            "https://pixiv.net/Artworks/1"
+        ));
+    }
+}
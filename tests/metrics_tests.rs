@@ -0,0 +1,117 @@
+#[cfg(all(test, feature = "gelbooru"))]
+mod metrics {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            cache::Cache,
+            client::{QueryDispatcher, WithClientBuilder},
+            metrics::Metrics,
+            transport::{Endpoint, HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+
+    const POSTS: &str = r#"{"post": [{"id": 1, "rating": "general"}]}"#;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Metrics for Recorder {
+        fn request(
+            &self,
+            backend: &str,
+            endpoint: Endpoint,
+            status: StatusCode,
+            _: Duration,
+            bytes: usize,
+        ) {
+            let event = format!("request {backend} {endpoint} {} {bytes}", status.as_u16());
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn error(&self, backend: &str, operation: &str, kind: &str) {
+            let event = format!("error {backend} {operation} {kind}");
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn cache(&self, backend: &str, endpoint: Endpoint, hit: bool) {
+            let event = format!("cache {backend} {endpoint} {hit}");
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_and_cache_are_reported() {
+        let recorder = Recorder::default();
+        let builder = GelbooruClient::builder()
+            .metrics(recorder.clone())
+            .cache(Cache::memory(16))
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async { Ok(HttpResponse::new(StatusCode::OK, POSTS)) })
+            .to_owned();
+
+        builder.dispatch().get().await.unwrap();
+        builder.dispatch().get().await.unwrap();
+
+        assert_eq!(
+            vec![
+                "cache gelbooru posts false".to_string(),
+                format!("request gelbooru posts 200 {}", POSTS.len()),
+                "cache gelbooru posts true".to_string(),
+            ],
+            *recorder.0.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_are_reported_by_kind() {
+        let recorder = Recorder::default();
+        let result = GelbooruClient::builder()
+            .metrics(recorder.clone())
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async { Ok(HttpResponse::new(StatusCode::NOT_FOUND, "")) })
+            .download("https://img3.gelbooru.com/images/missing.jpg")
+            .await;
+
+        assert!(matches!(result, Err(Error::NotFound)));
+        assert_eq!(
+            "error gelbooru download not_found",
+            recorder.0.lock().unwrap()[1]
+        );
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[tokio::test]
+    async fn prometheus_text_format() {
+        use rusty_booru::shared::metrics::PrometheusMetrics;
+
+        let metrics = PrometheusMetrics::new();
+        GelbooruClient::builder()
+            .metrics(metrics.clone())
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async { Ok(HttpResponse::new(StatusCode::OK, POSTS)) })
+            .dispatch()
+            .get()
+            .await
+            .unwrap();
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE booru_requests_total counter"));
+        assert!(text.contains(
+            r#"booru_requests_total{backend="gelbooru",endpoint="posts",status="200"} 1"#
+        ));
+        assert!(text.contains(
+            r#"booru_request_duration_seconds_count{backend="gelbooru",endpoint="posts"} 1"#
+        ));
+        assert!(text.contains(&format!(
+            r#"booru_response_bytes_total{{backend="gelbooru",endpoint="posts"}} {}"#,
+            POSTS.len()
+        )));
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(all(test, feature = "gelbooru"))]
+mod middleware {
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::{
+        header::{HeaderValue, AUTHORIZATION},
+        StatusCode,
+    };
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            client::{QueryDispatcher, WithClientBuilder},
+            middleware::Middleware,
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+
+    const POSTS: &str = r#"{"post": [{"id": 1, "rating": "general"}]}"#;
+
+    /// Records the order the hooks ran in.
+    struct Trace(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Trace {
+        fn before(&self, _: &mut HttpRequest) -> Result<Option<HttpResponse>, Error> {
+            self.1.lock().unwrap().push(format!("before {}", self.0));
+            Ok(None)
+        }
+
+        fn after(&self, _: &HttpRequest, _: &mut HttpResponse) -> Result<(), Error> {
+            self.1.lock().unwrap().push(format!("after {}", self.0));
+            Ok(())
+        }
+    }
+
+    struct Mirror;
+
+    impl Middleware for Mirror {
+        fn before(&self, request: &mut HttpRequest) -> Result<Option<HttpResponse>, Error> {
+            request.url = request.url.replace("gelbooru.com", "mirror.example");
+            request
+                .headers
+                .insert(AUTHORIZATION, HeaderValue::from_static("Basic dG9rZW4="));
+            Ok(None)
+        }
+    }
+
+    struct Offline;
+
+    impl Middleware for Offline {
+        fn before(&self, _: &mut HttpRequest) -> Result<Option<HttpResponse>, Error> {
+            Ok(Some(HttpResponse::new(StatusCode::OK, POSTS)))
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_run_around_the_request_in_order() {
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let sent = trace.clone();
+
+        GelbooruClient::builder()
+            .middleware(Trace("first", trace.clone()))
+            .middleware(Trace("second", trace.clone()))
+            .no_rate_limit()
+            .transport(move |_: HttpRequest| {
+                sent.lock().unwrap().push("send".to_string());
+                async move { Ok(HttpResponse::new(StatusCode::OK, POSTS)) }
+            })
+            .dispatch()
+            .get()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                "before first",
+                "before second",
+                "send",
+                "after second",
+                "after first"
+            ],
+            *trace.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_can_be_rewritten() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        GelbooruClient::builder()
+            .middleware(Mirror)
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                recorded.lock().unwrap().push(request);
+                async move { Ok(HttpResponse::new(StatusCode::OK, "fake image")) }
+            })
+            .download("https://img3.gelbooru.com/images/ab/cd/abcd.jpg")
+            .await
+            .unwrap();
+
+        let request = requests.lock().unwrap().remove(0);
+        assert_eq!(
+            "https://img3.mirror.example/images/ab/cd/abcd.jpg",
+            request.url
+        );
+        assert_eq!("Basic dG9rZW4=", request.headers[AUTHORIZATION]);
+    }
+
+    #[tokio::test]
+    async fn short_circuit_skips_the_transport() {
+        let trace = Arc::new(Mutex::new(Vec::new()));
+
+        let posts = GelbooruClient::builder()
+            .middleware(Trace("first", trace.clone()))
+            .middleware(Offline)
+            .middleware(Trace("never", trace.clone()))
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async { panic!("the request was sent") })
+            .dispatch()
+            .get()
+            .await;
+
+        assert_eq!(1, posts.unwrap().len());
+        assert_eq!(vec!["before first", "after first"], *trace.lock().unwrap());
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(all(test, feature = "danbooru", feature = "gelbooru"))]
+mod pages {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        danbooru::client::DanbooruClient,
+        gelbooru::client::GelbooruClient,
+        shared::{
+            client::WithClientBuilder,
+            transport::{HttpRequest, HttpResponse},
+        },
+    };
+
+    fn pid(request: &HttpRequest) -> u32 {
+        request
+            .query
+            .iter()
+            .find(|(k, _)| k == "pid")
+            .map(|(_, v)| v.parse().unwrap())
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn pages_are_merged_in_order_without_duplicates() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (counted, peak) = (in_flight.clone(), most.clone());
+
+        let pages = GelbooruClient::builder()
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                let pid = pid(&request);
+                let (in_flight, most) = (counted.clone(), peak.clone());
+                most.fetch_max(
+                    in_flight.fetch_add(1, Ordering::SeqCst) + 1,
+                    Ordering::SeqCst,
+                );
+
+                async move {
+                    // Later pages answer first, the order must come from the page numbers.
+                    tokio::time::sleep(Duration::from_millis(40 - 10 * pid as u64)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    // Each page repeats the last post of the previous one, as happens when
+                    // posts get added in the meantime.
+                    let body = match pid {
+                        2 => return Ok(HttpResponse::new(StatusCode::BAD_GATEWAY, "")),
+                        _ => format!(
+                            r#"{{"post": [{{"id": {}}}, {{"id": {}}}]}}"#,
+                            pid + 1,
+                            pid + 2
+                        ),
+                    };
+                    Ok(HttpResponse::new(StatusCode::OK, body))
+                }
+            })
+            .query(|q| q.tag("kafuu_chino").limit(2))
+            .get_first_pages(4, 2)
+            .await;
+
+        let ids = pages.posts.iter().map(|post| post.id).collect::<Vec<_>>();
+        assert_eq!(vec![1, 2, 3, 4, 5], ids);
+        assert_eq!(
+            vec![3],
+            pages
+                .failures
+                .iter()
+                .map(|(page, _)| *page)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, most.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn danbooru_pages_start_at_one() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        DanbooruClient::builder()
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                recorded.lock().unwrap().push(request.full_url());
+                async move { Ok(HttpResponse::new(StatusCode::OK, "[]")) }
+            })
+            .query(|q| q.tag("kafuu_chino").limit(5))
+            .get_pages([3], 1)
+            .await;
+
+        assert_eq!(
+            vec!["https://danbooru.donmai.us/posts.json?limit=5&tags=kafuu_chino&page=3"],
+            *requests.lock().unwrap()
+        );
+    }
+}
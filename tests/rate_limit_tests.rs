@@ -0,0 +1,92 @@
+#[cfg(all(test, feature = "gelbooru"))]
+mod rate_limit {
+    use std::{
+        num::NonZeroU32,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use reqwest::{
+        header::{HeaderValue, RETRY_AFTER},
+        StatusCode,
+    };
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            client::{QueryDispatcher, WithClientBuilder},
+            rate_limit::{RateLimit, RateLimiter},
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+
+    #[tokio::test]
+    async fn acquire_waits_once_burst_is_spent() {
+        let limiter = RateLimiter::new();
+        let limit = RateLimit::new(NonZeroU32::new(2).unwrap(), Duration::from_millis(200));
+
+        assert_eq!(Duration::ZERO, limiter.acquire("example.com", limit).await);
+        assert_eq!(Duration::ZERO, limiter.acquire("example.com", limit).await);
+        assert!(limiter.acquire("example.com", limit).await >= Duration::from_millis(90));
+
+        // Other hosts have a bucket of their own.
+        assert_eq!(Duration::ZERO, limiter.acquire("example.org", limit).await);
+    }
+
+    #[test]
+    #[should_panic(expected = "zero duration")]
+    fn limits_over_no_time_are_refused() {
+        RateLimit::new(NonZeroU32::MIN, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn clones_share_buckets() {
+        let mut builder = GelbooruClient::builder();
+        builder
+            .rate_limiter(RateLimiter::new())
+            .rate_limit(RateLimit::new(NonZeroU32::MIN, Duration::from_millis(300)))
+            .transport(|_: HttpRequest| async { Ok(HttpResponse::new(StatusCode::OK, "")) });
+
+        let other = builder.clone();
+        let start = Instant::now();
+
+        builder.dispatch().get().await.unwrap();
+        other.dispatch().get().await.unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_pauses_host() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+
+        let builder = GelbooruClient::builder()
+            .rate_limiter(RateLimiter::new())
+            .rate_limit(RateLimit::per_second(NonZeroU32::new(100).unwrap()))
+            .transport(move |_: HttpRequest| {
+                let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+                async move {
+                    Ok(if first {
+                        HttpResponse::new(StatusCode::TOO_MANY_REQUESTS, "")
+                            .header(RETRY_AFTER, HeaderValue::from_static("1"))
+                    } else {
+                        HttpResponse::new(StatusCode::OK, "")
+                    })
+                }
+            })
+            .to_owned();
+
+        let first = builder.dispatch().get().await;
+        assert!(matches!(first, Err(Error::RateLimited { .. })));
+
+        let start = Instant::now();
+        builder.dispatch().get().await.unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+}
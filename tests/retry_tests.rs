@@ -0,0 +1,163 @@
+#[cfg(all(test, feature = "danbooru"))]
+mod retry {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use reqwest::{
+        header::{HeaderValue, RETRY_AFTER},
+        Method, StatusCode,
+    };
+    use rusty_booru::{
+        danbooru::client::DanbooruClient,
+        shared::{
+            client::{ClientBuilder, QueryDispatcher, WithClientBuilder},
+            retry::{Jitter, RetryPolicy},
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+
+    /// Builder whose transport answers with `statuses` in order, counting the calls.
+    fn failing(
+        statuses: Vec<StatusCode>,
+        calls: Arc<AtomicUsize>,
+    ) -> ClientBuilder<DanbooruClient> {
+        DanbooruClient::builder()
+            .no_rate_limit()
+            .transport(move |_: HttpRequest| {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                let status = statuses[call.min(statuses.len() - 1)];
+                async move { Ok(HttpResponse::new(status, "[]")) }
+            })
+            .to_owned()
+    }
+
+    fn quick_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .max_attempts(3)
+            .backoff(Duration::from_millis(1), Duration::from_millis(5))
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let posts = failing(
+            vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::OK,
+            ],
+            calls.clone(),
+        )
+        .retry(quick_policy())
+        .dispatch()
+        .get()
+        .await;
+
+        assert!(posts.unwrap().is_empty());
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let posts = failing(vec![StatusCode::BAD_GATEWAY], calls.clone())
+            .retry(quick_policy())
+            .dispatch()
+            .get()
+            .await;
+
+        assert!(matches!(posts, Err(Error::Server { .. })));
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_by_default() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let file = failing(vec![StatusCode::BAD_GATEWAY, StatusCode::OK], calls.clone())
+            .download("https://cdn.donmai.us/original/image.jpg")
+            .await;
+
+        assert!(file.is_err());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn retries_downloads() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let file = failing(
+            vec![StatusCode::GATEWAY_TIMEOUT, StatusCode::OK],
+            calls.clone(),
+        )
+        .retry(quick_policy())
+        .download("https://cdn.donmai.us/original/image.jpg")
+        .await;
+
+        assert_eq!(b"[]".to_vec(), file.unwrap());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn never_retries_non_idempotent_requests() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.should_retry_status(&Method::GET, 1, StatusCode::BAD_GATEWAY));
+        assert!(!policy.should_retry_status(&Method::POST, 1, StatusCode::BAD_GATEWAY));
+        assert!(!policy.should_retry_status(&Method::GET, 1, StatusCode::NOT_FOUND));
+        assert!(!policy.should_retry_status(&Method::GET, 3, StatusCode::BAD_GATEWAY));
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::default()
+            .backoff(Duration::from_millis(100), Duration::from_millis(350))
+            .jitter(Jitter::None);
+
+        assert_eq!(Duration::from_millis(100), policy.delay(1));
+        assert_eq!(Duration::from_millis(200), policy.delay(2));
+        assert_eq!(Duration::from_millis(350), policy.delay(3));
+
+        let jittered = policy.jitter(Jitter::Equal);
+        for _ in 0..100 {
+            let delay = jittered.delay(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_is_left_to_the_caller() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+
+        let posts = DanbooruClient::builder()
+            .no_rate_limit()
+            .retry(quick_policy())
+            .transport(move |_: HttpRequest| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                async {
+                    Ok(HttpResponse::new(StatusCode::TOO_MANY_REQUESTS, "")
+                        .header(RETRY_AFTER, HeaderValue::from_static("3600")))
+                }
+            })
+            .dispatch()
+            .get()
+            .await;
+
+        assert!(matches!(
+            posts,
+            Err(Error::RateLimited { retry_after: Some(d) }) if d == Duration::from_secs(3600)
+        ));
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[should_panic(expected = "non-negative")]
+    fn negative_multipliers_are_refused() {
+        RetryPolicy::default().multiplier(-2.0);
+    }
+}
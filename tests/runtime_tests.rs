@@ -0,0 +1,146 @@
+#[cfg(all(test, feature = "gelbooru"))]
+mod runtime {
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+        time::{Duration, Instant},
+    };
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            client::{QueryDispatcher, WithClientBuilder},
+            retry::{Jitter, RetryPolicy},
+            runtime::{timeout, Runtime, ThreadRuntime},
+            transport::{HttpRequest, HttpResponse},
+        },
+    };
+
+    /// Polls `future` on the current thread, with no tokio runtime around.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct Unpark(Thread);
+
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut future = pin!(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[tokio::test]
+    async fn thread_runtime_sleeps() {
+        let started = Instant::now();
+        ThreadRuntime.sleep(Duration::from_millis(20)).await;
+
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn timeout_gives_up() {
+        let slow = ThreadRuntime.sleep(Duration::from_secs(5));
+        let fast = async { 1 };
+
+        assert_eq!(
+            None,
+            timeout(&ThreadRuntime, Duration::from_millis(10), slow).await
+        );
+        assert_eq!(
+            Some(1),
+            timeout(&ThreadRuntime, Duration::from_secs(5), fast).await
+        );
+    }
+
+    #[tokio::test]
+    async fn backoff_sleeps_through_the_builder_runtime() {
+        let sleeps = Arc::new(AtomicUsize::new(0));
+        let counted = sleeps.clone();
+
+        GelbooruClient::builder()
+            .no_rate_limit()
+            .no_timeouts()
+            .retry(RetryPolicy::default().jitter(Jitter::None))
+            .runtime(move |_| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                async {}
+            })
+            .transport(|_: HttpRequest| async {
+                Ok(HttpResponse::new(StatusCode::BAD_GATEWAY, ""))
+            })
+            .dispatch()
+            .get()
+            .await
+            .unwrap_err();
+
+        assert_eq!(2, sleeps.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn thread_runtime_sleeps_share_a_timer() {
+        let started = Instant::now();
+        let sleeps = (0..1000)
+            .map(|i| ThreadRuntime.sleep(Duration::from_millis(10 + i % 10)))
+            .collect::<Vec<_>>();
+
+        // Dropped sleeps are forgotten without holding up the others.
+        drop(ThreadRuntime.sleep(Duration::from_millis(5)));
+        block_on(futures_util::future::join_all(sleeps));
+
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(19));
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+    }
+
+    /// Without tokio, the builders need a runtime to sleep with and a transport that doesn't
+    /// rely on tokio's reactor like `ReqwestTransport` does.
+    #[test]
+    fn clients_run_without_tokio() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+
+        let posts = block_on(
+            GelbooruClient::builder()
+                .no_rate_limit()
+                .runtime(ThreadRuntime)
+                .retry(
+                    RetryPolicy::default()
+                        .backoff(Duration::from_millis(1), Duration::from_millis(10))
+                        .jitter(Jitter::None),
+                )
+                .transport(move |_: HttpRequest| {
+                    let status = match counted.fetch_add(1, Ordering::SeqCst) {
+                        0 => StatusCode::BAD_GATEWAY,
+                        _ => StatusCode::OK,
+                    };
+                    async move {
+                        Ok(HttpResponse::new(
+                            status,
+                            r#"{"post": [{"id": 1, "rating": "general"}]}"#,
+                        ))
+                    }
+                })
+                .dispatch()
+                .get(),
+        )
+        .unwrap();
+
+        assert_eq!(1, posts[0].id);
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+}
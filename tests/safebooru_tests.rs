@@ -1,9 +1,10 @@
 pub mod generic_tests;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "safebooru"))]
 mod safebooru {
     use rusty_booru::{
-        safebooru::{client::SafebooruClient, SafebooruRating},
+        generic::BooruPost,
+        safebooru::{client::SafebooruClient, SafebooruPost, SafebooruRating},
         shared::{
             client::{QueryDispatcher, WithClientBuilder},
             Sort,
@@ -99,6 +100,86 @@ mod safebooru {
         );
     }
 
+    fn sample_post() -> SafebooruPost {
+        serde_json::from_str(
+            r#"{
+                "id": 4683505,
+                "score": null,
+                "height": 1200,
+                "width": 800,
+                "hash": "d0e26173ad1896ca7c187c85a9d38f55329927b9",
+                "tags": "1girl kafuu_chino",
+                "image": "d0e26173ad1896ca7c187c85a9d38f55329927b9.jpg",
+                "directory": "4491",
+                "change": 1700000000,
+                "rating": "general"
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolve_urls_with_custom_base_url() {
+        let mut post = sample_post();
+        post.resolve_urls("http://localhost:8080");
+
+        assert_eq!(
+            "http://localhost:8080/images/4491/d0e26173ad1896ca7c187c85a9d38f55329927b9.jpg",
+            post.file_url.as_deref().unwrap()
+        );
+        assert_eq!(
+            "http://localhost:8080/thumbnails/4491/thumbnail_d0e26173ad1896ca7c187c85a9d38f55329927b9.jpg",
+            post.preview_url.as_deref().unwrap()
+        );
+
+        let post: BooruPost = post.into();
+        assert!(post.file_url.unwrap().starts_with("http://localhost:8080/"));
+    }
+
+    #[test]
+    fn deserialize_lenient_post() {
+        let post: SafebooruPost = serde_json::from_str(
+            r#"{
+                "id": 4683505,
+                "height": null,
+                "width": "800",
+                "hash": "d0e26173ad1896ca7c187c85a9d38f55329927b9",
+                "image": "d0e26173ad1896ca7c187c85a9d38f55329927b9.jpg",
+                "directory": 4491,
+                "change": "1700000000",
+                "rating": "general",
+                "owner": "danbooru"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(None, post.height);
+        assert_eq!(None, post.score);
+        assert_eq!(800, post.width);
+        assert_eq!("4491", post.directory);
+        assert_eq!("danbooru", post.extra["owner"]);
+    }
+
+    #[test]
+    fn unknown_ratings_and_missing_ids_are_not_guessed() {
+        let post: SafebooruPost = serde_json::from_str(r#"{"id": 1, "rating": "?"}"#).unwrap();
+        assert!(post.rating.is_none());
+        assert!(BooruPost::from(post).rating.is_none());
+
+        assert!(serde_json::from_str::<SafebooruPost>(r#"{"hash": "ff"}"#).is_err());
+        assert!(serde_json::from_str::<SafebooruPost>(r#"{"id": null}"#).is_err());
+    }
+
+    #[test]
+    fn resolve_urls_keeps_api_file_url() {
+        let mut post = sample_post();
+        post.file_url = Some("https://cdn.example.com/image.jpg".to_string());
+        post.resolve_urls("http://localhost:8080");
+
+        let post: BooruPost = post.into();
+        assert_eq!("https://cdn.example.com/image.jpg", post.file_url.unwrap());
+    }
+
     #[test]
     fn parse_rating_tags() {
         assert_eq!("safe", SafebooruRating::Safe.to_string());
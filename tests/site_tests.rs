@@ -0,0 +1,197 @@
+#[cfg(all(test, feature = "sites"))]
+mod site {
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        generic::{
+            client::{BooruOption, GenericClient},
+            Rating,
+        },
+        shared::{
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+        site::{client::Site, ResponseFormat, SiteConfig},
+    };
+
+    fn config() -> SiteConfig {
+        serde_json::from_str(
+            r#"{
+                "name": "clone",
+                "url": "http://localhost:3000",
+                "file_url": "{url}/images/{directory}/{image}",
+                "ratings": { "general": "safe", "explicit": "e" },
+                "autocomplete": { "path": "/autocomplete.php", "param": "q" }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    /// A site answering every request with `body`, recording the urls it was asked for.
+    fn site(config: SiteConfig, body: &'static str) -> (Site, Arc<Mutex<Vec<String>>>) {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = urls.clone();
+
+        let mut site = Site::new(config);
+        site.builder
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                recorded.lock().unwrap().push(request.full_url());
+                async move { Ok(HttpResponse::new(StatusCode::OK, body)) }
+            });
+
+        (site, urls)
+    }
+
+    #[tokio::test]
+    async fn json_posts_are_resolved_with_the_config() {
+        let (site, urls) = site(
+            config(),
+            r#"[{"id": "7", "hash": "abc", "directory": "12", "image": "abc.png", "rating": "e"}]"#,
+        );
+
+        let query = GenericClient::query()
+            .tag("kafuu_chino")
+            .rating(Rating::General)
+            .limit(3)
+            .to_owned();
+        let posts = site.get(&query).await.unwrap();
+
+        assert_eq!(
+            vec![concat!(
+                "http://localhost:3000/index.php?page=dapi&s=post&q=index&json=1",
+                "&limit=3&tags=kafuu_chino+rating%3Asafe"
+            )],
+            *urls.lock().unwrap()
+        );
+        assert_eq!(7, posts[0].id);
+        assert_eq!(Some("abc"), posts[0].md5());
+        assert_eq!(Some(Rating::Explicit), posts[0].rating);
+        assert_eq!(
+            Some("http://localhost:3000/images/12/abc.png"),
+            posts[0].file_url.as_deref()
+        );
+    }
+
+    #[tokio::test]
+    async fn gelbooru_style_answers_are_understood() {
+        let (site, _) = site(config(), r#"{"@attributes": {}, "post": [{"id": 1}]}"#);
+
+        let post = site.get_by_id(1).await.unwrap().unwrap();
+        assert_eq!(1, post.id);
+    }
+
+    #[tokio::test]
+    async fn xml_attributes_are_read() {
+        let mut config = config();
+        config.format = ResponseFormat::Xml;
+
+        let (site, urls) = site(
+            config,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <posts count="2" offset="0">
+                <post id="1" tags=" a&amp;b c " rating="safe" md5="ff" score="4"/>
+                <post id="2" tags="d" rating="questionable" file_url="http://cdn/2.png"/>
+            </posts>"#,
+        );
+
+        let posts = site.get(&GenericClient::query()).await.unwrap();
+
+        assert!(!urls.lock().unwrap()[0].contains("json"));
+        assert_eq!(" a&b c ", posts[0].tags);
+        assert_eq!(Some(Rating::General), posts[0].rating);
+        assert_eq!(4, posts[0].score);
+        assert_eq!(Some(Rating::Questionable), posts[1].rating);
+        assert_eq!(Some("http://cdn/2.png"), posts[1].file_url.as_deref());
+    }
+
+    #[tokio::test]
+    async fn xml_values_may_hold_brackets() {
+        let mut config = config();
+        config.format = ResponseFormat::Xml;
+
+        let (site, _) = site(
+            config,
+            r#"<posts count="1">
+                <post id="1" source="<b>pixiv</b>" tags='a>b' rating="s"/>
+            </posts>"#,
+        );
+
+        let posts = site.get(&GenericClient::query()).await.unwrap();
+        assert_eq!(1, posts.len());
+        assert_eq!(Some("<b>pixiv</b>"), posts[0].source.as_deref());
+        assert_eq!("a>b", posts[0].tags);
+        assert_eq!(None, posts[0].rating);
+    }
+
+    #[tokio::test]
+    async fn broken_xml_posts_are_reported() {
+        let mut config = config();
+        config.format = ResponseFormat::Xml;
+
+        let (site, _) = site(
+            config,
+            r#"<posts count="2">
+                <post id="1" rating="safe"/>
+                <post tags="no_id" rating="safe"/>
+            </posts>"#,
+        );
+
+        let result = site.get(&GenericClient::query()).await;
+        assert!(matches!(result, Err(Error::Decode { .. })), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn html_instead_of_xml_is_an_error() {
+        let mut config = config();
+        config.format = ResponseFormat::Xml;
+        let (site, _) = site(config, "<html><body>Down for maintenance</body></html>");
+
+        let result = site.get(&GenericClient::query()).await;
+        assert!(matches!(result, Err(Error::UnexpectedContentType { .. })));
+    }
+
+    #[tokio::test]
+    async fn autocomplete_uses_the_configured_endpoint() {
+        let (site, urls) = site(
+            config(),
+            r#"[{"label": "kafuu_chino (9)", "value": "kafuu_chino"}]"#,
+        );
+
+        let items = site
+            .get_autocomplete(&GenericClient::query(), "kaf")
+            .await
+            .unwrap();
+
+        assert_eq!("kafuu_chino", items[0].value);
+        assert_eq!(
+            vec!["http://localhost:3000/autocomplete.php?q=kaf"],
+            *urls.lock().unwrap()
+        );
+
+        let result = Site::new(SiteConfig::new("plain", "http://localhost:3000"))
+            .get_autocomplete(&GenericClient::query(), "kaf")
+            .await;
+        assert!(matches!(result, Err(Error::Unsupported("autocomplete"))));
+    }
+
+    #[tokio::test]
+    async fn sites_are_usable_through_the_generic_client() {
+        let (site, _) = site(config(), r#"[{"id": 3, "rating": "safe"}]"#);
+
+        let client = GenericClient::new();
+        client.register("clone", site);
+
+        let posts = client
+            .get(
+                &GenericClient::query(),
+                BooruOption::Custom("clone".to_string()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(3, posts[0].id);
+        assert_eq!(Some(Rating::General), posts[0].rating);
+    }
+}
@@ -0,0 +1,165 @@
+mod common;
+
+#[cfg(test)]
+mod tags {
+    use rusty_booru::generic::{
+        client::{BooruOption, GenericClient},
+        tags::{TagMapping, TagTable},
+        Rating,
+    };
+
+    use crate::common::{tagged, FakeBooru};
+
+    fn table() -> TagTable {
+        let mut table = TagTable::new();
+        table
+            .alias("chino_kafuu", "kafuu_chino")
+            .name(
+                "western",
+                "gochuumon_wa_usagi_desu_ka?",
+                "is_the_order_a_rabbit?",
+            )
+            .name("western", "kafuu_chino", "chino_(gochiusa)");
+        table
+    }
+
+    #[tokio::test]
+    async fn queries_and_posts_are_translated_per_booru() {
+        let western_booru = FakeBooru::new(vec![tagged(
+            1,
+            "1girl chino_(gochiusa) is_the_order_a_rabbit?",
+        )]);
+        let eastern_booru = FakeBooru::new(vec![tagged(1, "1girl chino_kafuu")]);
+
+        let client = crate::common::client([
+            ("western", western_booru.clone()),
+            ("eastern", eastern_booru.clone()),
+        ]);
+        client.tag_mapping(table());
+
+        let query = GenericClient::query()
+            .tag("chino_kafuu")
+            .blacklist_tag("gochuumon_wa_usagi_desu_ka?")
+            .rating(Rating::General)
+            .to_owned();
+
+        let western = client
+            .get(&query, BooruOption::Custom("western".into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![
+                "chino_(gochiusa)",
+                "-is_the_order_a_rabbit?",
+                "rating:general"
+            ],
+            western_booru.asked()
+        );
+        assert_eq!(
+            "1girl kafuu_chino gochuumon_wa_usagi_desu_ka?",
+            western[0].tags
+        );
+
+        let eastern = client
+            .get(&query, BooruOption::Custom("eastern".into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![
+                "kafuu_chino",
+                "-gochuumon_wa_usagi_desu_ka?",
+                "rating:general"
+            ],
+            eastern_booru.asked()
+        );
+        assert_eq!("1girl kafuu_chino", eastern[0].tags);
+
+        let items = client
+            .get_autocomplete(
+                &GenericClient::query(),
+                BooruOption::Custom("western".into()),
+                "is_the_order_a",
+            )
+            .await
+            .unwrap();
+        assert_eq!("gochuumon_wa_usagi_desu_ka?", items[0].value);
+        assert_eq!("gochuumon_wa_usagi_desu_ka?", items[0].label);
+    }
+
+    #[tokio::test]
+    async fn mappings_set_on_the_shared_client_apply_to_queries() {
+        let booru = FakeBooru::new(vec![tagged(1, "chino_(gochiusa)")]);
+        let mut table = table();
+        table.name("shared_western", "kafuu_chino", "chino_(gochiusa)");
+        GenericClient::shared()
+            .tag_mapping(table)
+            .register("shared_western", booru.clone());
+
+        let posts = GenericClient::query()
+            .tag("chino_kafuu")
+            .get(BooruOption::Custom("shared_western".into()))
+            .await
+            .unwrap();
+
+        assert_eq!(vec!["chino_(gochiusa)"], booru.asked());
+        assert_eq!("kafuu_chino", posts[0].tags);
+    }
+
+    #[tokio::test]
+    async fn tags_are_verbatim_without_a_mapping() {
+        let western_booru = FakeBooru::new(vec![tagged(1, "chino_(gochiusa)")]);
+        let client = crate::common::client([("western", western_booru.clone())]);
+
+        let query = GenericClient::query().tag("chino_kafuu").to_owned();
+        let posts = client
+            .get(&query, BooruOption::Custom("western".into()))
+            .await
+            .unwrap();
+
+        assert_eq!(vec!["chino_kafuu"], western_booru.asked());
+        assert_eq!("chino_(gochiusa)", posts[0].tags);
+    }
+
+    #[test]
+    fn tables_are_seeded_from_danbooru_aliases_and_user_tables() {
+        let mut table = TagTable::new();
+        table
+            .danbooru_aliases(
+                r#"[
+                    {
+                        "antecedent_name": "chino_kafuu",
+                        "consequent_name": "kafuu_chino",
+                        "status": "active"
+                    },
+                    {
+                        "antecedent_name": "cocoa",
+                        "consequent_name": "hoto_cocoa",
+                        "status": "deleted"
+                    }
+                ]"#,
+            )
+            .unwrap();
+
+        let user: TagTable = serde_json::from_str(
+            r#"{
+                "aliases": { "cocoa": "hoto_cocoa" },
+                "boorus": { "gelbooru": { "chino_(gochiusa)": "kafuu_chino" } }
+            }"#,
+        )
+        .unwrap();
+        table.extend(user);
+
+        assert_eq!("kafuu_chino", table.normalize("danbooru", "chino_kafuu"));
+        assert_eq!("hoto_cocoa", table.normalize("danbooru", "cocoa"));
+        assert_eq!(
+            "kafuu_chino",
+            table.normalize("gelbooru", "chino_(gochiusa)")
+        );
+        assert_eq!(
+            "chino_(gochiusa)",
+            table.translate("gelbooru", "chino_kafuu")
+        );
+        assert_eq!("kafuu_chino", table.translate("danbooru", "chino_kafuu"));
+        assert_eq!("1girl", table.translate("gelbooru", "1girl"));
+    }
+}
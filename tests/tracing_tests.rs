@@ -0,0 +1,157 @@
+#[cfg(all(test, feature = "tracing", feature = "gelbooru"))]
+mod tracing_spans {
+    use std::{
+        collections::HashMap,
+        fmt::Debug,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use reqwest::StatusCode;
+    use rusty_booru::{
+        gelbooru::client::GelbooruClient,
+        shared::{
+            client::{QueryDispatcher, WithClientBuilder},
+            retry::RetryPolicy,
+            transport::{HttpRequest, HttpResponse},
+            Error,
+        },
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    type Spans = Arc<Mutex<HashMap<u64, (String, HashMap<String, String>)>>>;
+    type Events = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+    /// Keeps the fields of every span and event it sees.
+    #[derive(Default)]
+    struct Recorder {
+        next: AtomicU64,
+        spans: Spans,
+        events: Events,
+    }
+
+    struct Fields<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(
+                field.name().to_string(),
+                format!("{value:?}").replace('"', ""),
+            );
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.next.fetch_add(1, Ordering::SeqCst) + 1;
+            let mut fields = HashMap::new();
+            span.record(&mut Fields(&mut fields));
+
+            self.spans
+                .lock()
+                .unwrap()
+                .insert(id, (span.metadata().name().to_string(), fields));
+            Id::from_u64(id)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            if let Some((_, fields)) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+                values.record(&mut Fields(fields));
+            }
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = HashMap::new();
+            event.record(&mut Fields(&mut fields));
+            self.events.lock().unwrap().push(fields);
+        }
+
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn span(spans: &Spans, name: &str) -> HashMap<String, String> {
+        spans
+            .lock()
+            .unwrap()
+            .values()
+            .find(|(span, _)| span == name)
+            .map(|(_, fields)| fields.clone())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn calls_and_requests_get_spans() {
+        let recorder = Recorder::default();
+        let spans = recorder.spans.clone();
+        let _guard = tracing::subscriber::set_default(recorder);
+
+        GelbooruClient::builder()
+            .query_param("api_key", "hunter2")
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async {
+                Ok(HttpResponse::new(
+                    StatusCode::OK,
+                    r#"{"post": [{"id": 1, "rating": "general"}]}"#,
+                ))
+            })
+            .query(|q| q.tag("kafuu_chino").limit(5))
+            .get()
+            .await
+            .unwrap();
+
+        let query = span(&spans, "booru_query");
+        assert_eq!("gelbooru", query["backend"]);
+        assert_eq!("get", query["operation"]);
+        assert_eq!("kafuu_chino", query["tags"]);
+        assert_eq!("1", query["results"]);
+
+        let request = span(&spans, "booru_request");
+        assert_eq!("posts", request["endpoint"]);
+        assert_eq!("200", request["status"]);
+        assert_eq!("1", request["attempts"]);
+        assert!(request["url"].contains("api_key=REDACTED"));
+        assert!(!request["url"].contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn failures_are_recorded_without_credentials() {
+        let recorder = Recorder::default();
+        let spans = recorder.spans.clone();
+        let events = recorder.events.clone();
+        let _guard = tracing::subscriber::set_default(recorder);
+
+        // Like the errors of transports that put the url in their messages.
+        GelbooruClient::builder()
+            .query_param("api_key", "hunter2")
+            .no_rate_limit()
+            .retry(RetryPolicy::none())
+            .transport(|request: HttpRequest| async move {
+                Err(Error::InvalidQuery(request.full_url()))
+            })
+            .dispatch()
+            .get()
+            .await
+            .unwrap_err();
+
+        assert_eq!("invalid_query", span(&spans, "booru_request")["error"]);
+        assert_eq!("invalid_query", span(&spans, "booru_query")["error"]);
+
+        let spans = format!("{:?}", spans.lock().unwrap());
+        let events = format!("{:?}", events.lock().unwrap());
+        assert!(events.contains("booru call failed"));
+        assert!(!spans.contains("hunter2"));
+        assert!(!events.contains("hunter2"));
+    }
+}
@@ -0,0 +1,212 @@
+#[cfg(all(
+    test,
+    feature = "danbooru",
+    feature = "gelbooru",
+    feature = "safebooru"
+))]
+mod transport {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use reqwest::{
+        header::{HeaderName, HeaderValue, CONTENT_TYPE, RETRY_AFTER, USER_AGENT},
+        StatusCode,
+    };
+    use rusty_booru::{
+        danbooru::client::{DanbooruClient, DanbooruError},
+        gelbooru::client::GelbooruClient,
+        safebooru::client::SafebooruClient,
+        shared::{
+            client::{QueryDispatcher, WithClientBuilder},
+            transport::{HttpRequest, HttpResponse, HttpTransport},
+            Error,
+        },
+    };
+
+    fn respond(response: HttpResponse) -> impl HttpTransport {
+        move |_: HttpRequest| {
+            let response = response.clone();
+            async move { Ok(response) }
+        }
+    }
+
+    fn json(status: StatusCode, body: &str) -> HttpResponse {
+        HttpResponse::new(status, body).header(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/json; charset=utf-8"),
+        )
+    }
+
+    #[tokio::test]
+    async fn get_posts_through_custom_transport() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        let posts = GelbooruClient::builder()
+            .default_url("http://localhost:8080")
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                recorded.lock().unwrap().push(request.full_url());
+                async move {
+                    Ok(json(
+                        StatusCode::OK,
+                        r#"{"post": [{"id": 1, "md5": "abc", "rating": "general"}]}"#,
+                    ))
+                }
+            })
+            .query(|q| q.tag("kafuu_chino").limit(3))
+            .get()
+            .await
+            .unwrap();
+
+        assert_eq!(1, posts.len());
+        assert_eq!("abc", posts[0].md5);
+        assert_eq!(
+            vec!["http://localhost:8080/index.php?page=dapi&s=post&q=index&json=1&limit=3&tags=kafuu_chino"],
+            *requests.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_body_is_no_posts() {
+        let posts = SafebooruClient::builder()
+            .no_rate_limit()
+            .transport(respond(HttpResponse::new(StatusCode::OK, "")))
+            .query(|q| q.tag("nothing_here"))
+            .get()
+            .await;
+
+        assert!(posts.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rate_limited_with_retry_after() {
+        let posts = GelbooruClient::builder()
+            .no_rate_limit()
+            .transport(respond(
+                HttpResponse::new(StatusCode::TOO_MANY_REQUESTS, "")
+                    .header(RETRY_AFTER, HeaderValue::from_static("30")),
+            ))
+            .query(|q| q.tag("kafuu_chino"))
+            .get()
+            .await;
+
+        assert!(matches!(
+            posts,
+            Err(Error::RateLimited { retry_after: Some(d) }) if d == Duration::from_secs(30)
+        ));
+    }
+
+    #[tokio::test]
+    async fn html_page_is_unexpected_content_type() {
+        let posts = GelbooruClient::builder()
+            .no_rate_limit()
+            .transport(respond(
+                HttpResponse::new(StatusCode::OK, "<html><body>Database is down</body></html>")
+                    .header(CONTENT_TYPE, HeaderValue::from_static("text/html")),
+            ))
+            .query(|q| q.tag("kafuu_chino"))
+            .get()
+            .await;
+
+        assert!(matches!(
+            posts,
+            Err(Error::UnexpectedContentType { snippet, .. }) if snippet.contains("Database is down")
+        ));
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_decode_error() {
+        let posts = GelbooruClient::builder()
+            .no_rate_limit()
+            .transport(respond(json(StatusCode::OK, r#"{"post": "#)))
+            .query(|q| q.tag("kafuu_chino"))
+            .get()
+            .await;
+
+        assert!(matches!(
+            posts,
+            Err(Error::Decode { url, .. }) if url.starts_with("https://gelbooru.com/index.php?")
+        ));
+    }
+
+    #[tokio::test]
+    async fn danbooru_error_body() {
+        let posts = DanbooruClient::builder()
+            .no_rate_limit()
+            .transport(respond(json(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                r#"{"success": false, "error": "PostQuery::TagLimitError", "message": "You cannot search for more than 2 tags at a time."}"#,
+            )))
+            .query(|q| q.tag("kafuu_chino").tag("loli").tag("maid"))
+            .get()
+            .await;
+
+        assert!(matches!(
+            posts,
+            Err(Error::Danbooru(DanbooruError::TagLimitError { status, .. }))
+                if status == StatusCode::UNPROCESSABLE_ENTITY
+        ));
+    }
+
+    #[tokio::test]
+    async fn danbooru_missing_post_is_none() {
+        let post = DanbooruClient::builder()
+            .no_rate_limit()
+            .transport(respond(json(
+                StatusCode::NOT_FOUND,
+                r#"{"success": false, "error": "ActiveRecord::RecordNotFound", "message": "That record was not found."}"#,
+            )))
+            .dispatch()
+            .get_by_id(1)
+            .await;
+
+        assert!(post.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn connection_settings_apply_to_every_request() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        DanbooruClient::builder()
+            .user_agent(HeaderValue::from_static("my-bot/1.0"))
+            .header(
+                HeaderName::from_static("x-mirror"),
+                HeaderValue::from_static("eu"),
+            )
+            .query_param("api_key", "secret")
+            .no_rate_limit()
+            .transport(move |request: HttpRequest| {
+                recorded.lock().unwrap().push(request);
+                async move { Ok(json(StatusCode::OK, "[]")) }
+            })
+            .dispatch()
+            .get_autocomplete("kafuu")
+            .await
+            .unwrap();
+
+        let request = requests.lock().unwrap().remove(0);
+        assert_eq!("my-bot/1.0", request.headers[USER_AGENT]);
+        assert_eq!("eu", request.headers["x-mirror"]);
+        assert!(request.full_url().ends_with("&api_key=secret"));
+    }
+
+    #[tokio::test]
+    async fn slow_response_times_out() {
+        let posts = GelbooruClient::builder()
+            .timeout(Duration::from_millis(10))
+            .no_rate_limit()
+            .transport(|_: HttpRequest| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(json(StatusCode::OK, "[]"))
+            })
+            .dispatch()
+            .get()
+            .await;
+
+        assert!(matches!(posts, Err(Error::Timeout(_))));
+    }
+}